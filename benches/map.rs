@@ -6,40 +6,122 @@ pub fn map_initialization(_c: &mut Criterion) {}
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn map_initialization(c: &mut Criterion) {
-    use terrarust::{map::*, blocks::*, chunks::*};
+    use terrarust::world::*;
     use criterion::black_box;
 
     fn function(_useless: usize) {
-        let mut map = Map {
-            chunks: Vec::new(),
-            first_chunk_number: -5,
-            first_block: 0,
-            air: Block {
-                block_type: BlockType::Air,
-                natural_background: NaturalBackground::Sky,
-                light: 0,
-            },
-            to_update_chunks: Vec::new(),
-            light_update: Vec::new(),
-        };
-
-        let mut height: f64 = 20.0;
-        let mut slope: f64 = 0.2;
-        for i in -5..5 {
-
-            map.chunks.push((
-                Chunk::generate(&mut height, &mut slope, true, i * 32),
-                (),
-                (),
-                Vec::new(),
-                Vec::new(),
-            ));
-        }
-
-        map.init_lights();
+        black_box(World::default());
     }
     c.bench_function("map initialization", |b| b.iter(|| function(black_box(0))));
 }
 
-criterion_group!(benches, map_initialization);
-criterion_main!(benches);
\ No newline at end of file
+#[cfg(target_arch = "wasm32")]
+pub fn light_initialization(_c: &mut Criterion) {}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn light_initialization(c: &mut Criterion) {
+    use terrarust::{chunks::Chunk, seed::WorldSeed, world::*};
+
+    // every section of the 10 chunks is generated, not only the ones around the player
+    let seed = WorldSeed::default();
+    let mut world = World::from_chunks(seed, -5, (-5..5).map(|chunk_number| Chunk::generate(seed, chunk_number)).collect());
+    let mut group = c.benchmark_group("light initialization");
+    // lighting entire chunks takes a while
+    group.sample_size(10);
+    group.bench_function("10 chunks", |b| b.iter(|| world.init_lights()));
+    group.finish();
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn chunk_storage(_c: &mut Criterion) {}
+
+/// Compares the chunk layout used before blocks were packed, a `Vec<[Block; 2048]>` of 24 bytes blocks built on the stack column by column,
+/// with the current one when every section has its own blocks, which is the most memory a chunk can take.
+/// The memory they take is checked by the `chunks_only_allocate_the_blocks_of_varied_sections` test.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn chunk_storage(c: &mut Criterion) {
+    use criterion::black_box;
+    use terrarust::{
+        blocks::{Block, BlockType, NaturalBackground},
+        chunks::{Chunk, Section, CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_COUNT, SECTION_SIZE},
+        fluid::Fluid,
+        light::Light,
+        seed::WorldSeed,
+    };
+
+    #[derive(Clone, Copy)]
+    #[allow(dead_code)]
+    struct LegacyBlock {
+        block_type: BlockType,
+        natural_background: NaturalBackground,
+        light: usize,
+        water: f64,
+    }
+
+    let legacy_block = LegacyBlock {
+        block_type: BlockType::AIR,
+        natural_background: NaturalBackground::Sky,
+        light: 0,
+        water: 0.0,
+    };
+    let block = Block {
+        block_type: BlockType::AIR,
+        natural_background: NaturalBackground::Sky,
+        light: Light::BLACK,
+        fluid: Fluid::Water,
+        fluid_level: 0,
+    };
+
+    let legacy = || {
+        let mut blocks = Vec::new();
+        for _ in 0..CHUNK_WIDTH {
+            blocks.push([black_box(legacy_block); CHUNK_HEIGHT]);
+        }
+        blocks
+    };
+    let packed = || {
+        let mut chunk = Chunk::new();
+        for section in 0..SECTION_COUNT {
+            chunk.set_section(section, Section::Full(vec![black_box(block); SECTION_SIZE * SECTION_SIZE]));
+        }
+        chunk
+    };
+
+    let mut group = c.benchmark_group("chunk allocation");
+    group.bench_function("legacy", |b| b.iter(|| black_box(legacy())));
+    group.bench_function("packed", |b| b.iter(|| black_box(packed())));
+    group.finish();
+
+    // the generator used to write every block of a chunk into its columns of 2048 blocks
+    let legacy_generation = |chunk_number| {
+        let chunk = Chunk::generate(WorldSeed::default(), chunk_number);
+        let mut blocks = Vec::new();
+        for x in 0..CHUNK_WIDTH {
+            let mut column = [legacy_block; CHUNK_HEIGHT];
+            for (y, legacy) in column.iter_mut().enumerate() {
+                let block = chunk.get(x, y).unwrap();
+                *legacy = LegacyBlock {
+                    block_type: block.block_type,
+                    natural_background: block.natural_background,
+                    light: block.light.brightness() as usize,
+                    water: block.fluid_level as f64,
+                };
+            }
+            blocks.push(column);
+        }
+        blocks
+    };
+
+    let mut group = c.benchmark_group("chunk generation");
+    group.sample_size(10);
+    group.bench_function("legacy", |b| b.iter(|| black_box(legacy_generation(black_box(3)))));
+    group.bench_function("packed", |b| b.iter(|| black_box(Chunk::generate(WorldSeed::default(), black_box(3)))));
+    group.finish();
+
+    c.bench_function("section generation", |b| {
+        b.iter(|| black_box(Section::generate(WorldSeed::default(), black_box(3), black_box(4))))
+    });
+}
+
+criterion_group!(benches, map_initialization, light_initialization, chunk_storage);
+criterion_main!(benches);
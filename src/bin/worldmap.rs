@@ -0,0 +1,47 @@
+//! Generates a region of a world and writes its map as a PNG file, one pixel per block.
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    use std::path::Path;
+    use terrarust::{
+        biomes::biome_at,
+        chunks::CHUNK_WIDTH,
+        export::{generate_region, render_region, ExportOptions, USAGE},
+        software_renderer::SoftwareTextures,
+    };
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match ExportOptions::parse(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let world = generate_region(&options);
+    if options.settle_fluids && !world.are_fluids_settled() {
+        eprintln!("the fluids are still flowing after {} ticks", world.fluid_ticks);
+    }
+    let textures = SoftwareTextures::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("pkg"));
+    let image = render_region(&world, &options, &textures);
+    if let Err(error) = std::fs::write(&options.output, image.encode_png()) {
+        eprintln!("could not write {}: {}", options.output, error);
+        std::process::exit(1);
+    }
+
+    // the biomes from left to right, to read the band of the map
+    let columns = options.chunks.start * CHUNK_WIDTH as isize..options.chunks.end * CHUNK_WIDTH as isize;
+    let mut start = columns.start;
+    for x in columns.clone() {
+        let biome = biome_at(options.seed, x);
+        if x + 1 == columns.end || biome_at(options.seed, x + 1) != biome {
+            println!("{:>8}..{:<8} {:?}", start, x + 1, biome);
+            start = x + 1;
+        }
+    }
+    println!("wrote {} ({}x{})", options.output, image.width, image.height);
+}
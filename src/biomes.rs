@@ -0,0 +1,188 @@
+//! Biomes and the climate they come from.
+//!
+//! Temperature and humidity are two slow noises along the world. Each column gets the biome of its climate,
+//! so that biomes with close climates, like deserts and grasslands, tend to be neighbours.
+//! The lookups only depend on the seed and on the column, and can be used by any system, like spawning or weather.
+
+use crate::{noise::fractal_noise_1d, seed::WorldSeed, world::World};
+use serde::Deserialize;
+
+const TEMPERATURE_NOISE_LAYER: u32 = 300;
+const HUMIDITY_NOISE_LAYER: u32 = 301;
+/// Columns over which the climate goes from one extreme to the other.
+const CLIMATE_SCALE: f64 = 600.0;
+/// Climates further than this from 0 are cold or hot, and dry or wet.
+const CLIMATE_THRESHOLD: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Biome {
+    Hills,
+    Grassland,
+    TemperateBroadleafForest,
+    Desert,
+    Snow,
+    Swamp,
+    Mountains,
+}
+
+/// Temperature and humidity of a column, both in `[-1, 1]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Climate {
+    pub temperature: f64,
+    pub humidity: f64,
+}
+
+impl Climate {
+    pub fn get_biome(&self) -> Biome {
+        let (cold, hot) = (self.temperature < -CLIMATE_THRESHOLD, self.temperature > CLIMATE_THRESHOLD);
+        let (dry, wet) = (self.humidity < -CLIMATE_THRESHOLD, self.humidity > CLIMATE_THRESHOLD);
+        match (cold, hot, dry, wet) {
+            (true, _, true, _) => Biome::Mountains,
+            (true, _, _, _) => Biome::Snow,
+            (_, true, true, _) => Biome::Desert,
+            (_, true, _, true) => Biome::Swamp,
+            (_, _, true, _) => Biome::Hills,
+            (_, _, _, true) => Biome::TemperateBroadleafForest,
+            _ => Biome::Grassland,
+        }
+    }
+}
+
+pub fn climate_at(seed: WorldSeed, x: isize) -> Climate {
+    // the noise rarely goes far from 0 so it is stretched to use the whole range
+    let noise = |layer| (fractal_noise_1d(seed, layer, x as f64 / CLIMATE_SCALE, 3) * 2.0).clamp(-1.0, 1.0);
+    Climate {
+        temperature: noise(TEMPERATURE_NOISE_LAYER),
+        humidity: noise(HUMIDITY_NOISE_LAYER),
+    }
+}
+
+pub fn biome_at(seed: WorldSeed, x: isize) -> Biome {
+    climate_at(seed, x).get_biome()
+}
+
+impl World {
+    pub fn get_climate(&self, x: isize) -> Climate {
+        climate_at(self.seed, x)
+    }
+
+    pub fn get_biome(&self, x: isize) -> Biome {
+        biome_at(self.seed, x)
+    }
+}
+
+impl Biome {
+    pub fn get_frequency(&self) -> f64 {
+        match self {
+            Biome::Hills => 0.2,
+            Biome::Grassland => 0.06,
+            Biome::TemperateBroadleafForest => 0.08,
+            Biome::Desert => 0.05,
+            Biome::Snow => 0.1,
+            Biome::Swamp => 0.04,
+            Biome::Mountains => 0.3,
+        }
+    }
+
+    pub fn get_max_slope(&self) -> f64 {
+        match self {
+            Biome::Hills => 0.9,
+            Biome::Grassland => 0.5,
+            Biome::TemperateBroadleafForest => 0.7,
+            Biome::Desert => 0.4,
+            Biome::Snow => 0.7,
+            Biome::Swamp => 0.2,
+            Biome::Mountains => 1.6,
+        }
+    }
+
+    /// Range of the surface height, smaller values being higher.
+    pub fn get_height(&self) -> std::ops::Range<f64> {
+        match self {
+            Biome::Hills => 26.0..36.0,
+            Biome::Grassland => 32.0..38.0,
+            Biome::TemperateBroadleafForest => 30.0..38.0,
+            Biome::Desert => 32.0..40.0,
+            Biome::Snow => 28.0..36.0,
+            Biome::Swamp => 38.0..42.0,
+            Biome::Mountains => 10.0..26.0,
+        }
+    }
+
+    /// How many caves there are under this biome, 1.0 being the average.
+    pub fn get_cave_density(&self) -> f64 {
+        match self {
+            Biome::Hills => 1.3,
+            Biome::Grassland => 0.8,
+            Biome::TemperateBroadleafForest => 1.0,
+            Biome::Desert => 0.9,
+            Biome::Snow => 1.0,
+            Biome::Swamp => 0.6,
+            Biome::Mountains => 1.4,
+        }
+    }
+
+    /// One column out of this many has a tree, none if 0.
+    pub fn get_tree_prob(&self) -> u16 {
+        match self {
+            Biome::Hills => 50,
+            Biome::Grassland => 32,
+            Biome::TemperateBroadleafForest => 10,
+            Biome::Desert => 0,
+            Biome::Snow => 24,
+            Biome::Swamp => 14,
+            Biome::Mountains => 80,
+        }
+    }
+
+    /// One region out of this many has a lake or a river, none if 0.
+    pub fn get_water_prob(&self) -> u64 {
+        match self {
+            Biome::Hills => 3,
+            Biome::Grassland => 3,
+            Biome::TemperateBroadleafForest => 2,
+            Biome::Desert => 8,
+            Biome::Snow => 4,
+            Biome::Swamp => 1,
+            Biome::Mountains => 3,
+        }
+    }
+
+    /// Heights of the trunks of the trees.
+    pub fn get_tree_height(&self) -> std::ops::Range<isize> {
+        match self {
+            Biome::Hills => 5..8,
+            Biome::Grassland => 4..7,
+            Biome::TemperateBroadleafForest => 7..12,
+            Biome::Desert => 3..5,
+            Biome::Snow => 6..10,
+            Biome::Swamp => 4..7,
+            Biome::Mountains => 4..6,
+        }
+    }
+
+    /// The block at the surface and the one filling the layer down to the stone.
+    pub fn get_soil(&self) -> (&'static str, &'static str) {
+        match self {
+            Biome::Desert => ("sand", "sand"),
+            Biome::Snow => ("snow", "dirt"),
+            Biome::Swamp => ("mud", "mud"),
+            Biome::Mountains => ("stone", "stone"),
+            _ => ("grass", "dirt"),
+        }
+    }
+
+    /// Color of the biome on exported maps.
+    pub fn get_map_color(&self) -> [u8; 3] {
+        match self {
+            Biome::Hills => [120, 170, 70],
+            Biome::Grassland => [170, 210, 90],
+            Biome::TemperateBroadleafForest => [40, 120, 50],
+            Biome::Desert => [235, 210, 140],
+            Biome::Snow => [240, 245, 255],
+            Biome::Swamp => [90, 110, 70],
+            Biome::Mountains => [140, 135, 130],
+        }
+    }
+}
@@ -1,11 +1,16 @@
-use crate::items::Item;
+use crate::{fluid::Fluid, items::Item, light::Light, textures::Sprite};
+use serde::Deserialize;
+use std::sync::LazyLock;
 
-#[derive(PartialEq, Clone, Debug)]
+/// Kept to 10 bytes since there are 65536 of them in each chunk.
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub struct Block {
     pub block_type: BlockType,
     pub natural_background: NaturalBackground,
-    pub light: usize,
-    pub water: f64,
+    pub light: Light,
+    pub fluid: Fluid,
+    /// Level of the fluid, `FLUID_FULL` for a full block.
+    pub fluid_level: u16,
 }
 
 impl Block {
@@ -13,59 +18,170 @@ impl Block {
         Block {
             block_type,
             natural_background,
-            light: 0,
-            water: 0.0,
+            light: Light::BLACK,
+            fluid: Fluid::Water,
+            fluid_level: 0,
         }
     }
 
     pub fn can_pass_through(&self) -> bool {
-        self.block_type.clone().can_pass_through()
+        self.block_type.can_pass_through()
     }
 
     pub fn as_item(&self) -> Vec<Item> {
         self.block_type.as_item()
     }
+
+    /// Light emitted by the block and by the fluid it holds.
+    pub fn get_light_emission(&self) -> Light {
+        self.block_type.get_light_emission().max(self.fluid.get_light_emission(self.fluid_level))
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum BlockType {
-    Grass,
-    Air,
-    Dirt,
-    Tree,
+/// The definitions of every block, loaded from `ressources/blocks.json`.
+pub static BLOCKS: LazyLock<BlockRegistry> = LazyLock::new(|| {
+    BlockRegistry::from_json(include_str!("../pkg/ressources/blocks.json")).unwrap()
+});
+
+/// How a block is drawn.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum BlockTexture {
+    /// 16 tiles of the block atlas starting at these tile coordinates, one for each combination of borders touching a block that can be passed through.
+    Atlas { atlas: (u16, u16) },
+    /// A whole sprite drawn with an offset from the top left corner of the block.
+    Sprite { sprite: Sprite, offset: (f64, f64) },
 }
 
-impl BlockType {
-    pub fn can_pass_through(&self) -> bool {
-        match self {
-            BlockType::Grass => false,
-            BlockType::Dirt => false,
-            BlockType::Air => true,
-            BlockType::Tree => true,
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockProperties {
+    pub name: String,
+    pub solid: bool,
+    pub light_loss: u8,
+    /// Red, green and blue light emitted by the block.
+    pub light_emission: Light,
+    /// Seconds needed to break the block.
+    pub hardness: f64,
+    /// Whether the block falls when the block under it is removed.
+    #[serde(default)]
+    pub falls: bool,
+    pub drops: Vec<Item>,
+    pub texture: Option<BlockTexture>,
+}
+
+pub struct BlockRegistry {
+    blocks: Vec<BlockProperties>,
+}
+
+impl BlockRegistry {
+    /// Reads a list of block definitions. Air must be the first one.
+    pub fn from_json(json: &str) -> Result<BlockRegistry, String> {
+        let blocks: Vec<BlockProperties> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if blocks.first().map(|block| block.name.as_str()) != Some("air") {
+            return Err(String::from("the first block must be air"));
         }
+        if blocks.len() > u16::MAX as usize {
+            return Err(format!("too many blocks ({})", blocks.len()));
+        }
+        for (idx, block) in blocks.iter().enumerate() {
+            if blocks[..idx].iter().any(|previous| previous.name == block.name) {
+                return Err(format!("{} is defined twice", block.name));
+            }
+        }
+        Ok(BlockRegistry { blocks })
     }
 
-    pub fn as_item(&self) -> Vec<Item> {
-        match self {
-            BlockType::Grass => vec![Item::Dirt],
-            BlockType::Dirt => vec![Item::Dirt],
-            BlockType::Air => vec![],
-            BlockType::Tree => vec![Item::Log, Item::WoodStick, Item::Foliage],
-        }
+    pub fn get_by_name(&self, name: &str) -> Option<BlockType> {
+        self.blocks
+            .iter()
+            .position(|block| block.name == name)
+            .map(|idx| BlockType(idx as u16))
     }
 
-    pub fn get_light_loss(&self) -> usize {
-        match self {
-            BlockType::Grass => 6,
-            BlockType::Dirt => 10,
-            BlockType::Air => 1,
-            BlockType::Tree => 1,
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+impl std::ops::Index<BlockType> for BlockRegistry {
+    type Output = BlockProperties;
+
+    fn index(&self, block_type: BlockType) -> &BlockProperties {
+        &self.blocks[block_type.0 as usize]
+    }
+}
+
+/// The id of a block in the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockType(pub u16);
+
+impl BlockType {
+    pub const AIR: BlockType = BlockType(0);
+
+    /// Panics if no block has this name.
+    pub fn from_name(name: &str) -> BlockType {
+        match BLOCKS.get_by_name(name) {
+            Some(block_type) => block_type,
+            None => panic!("there is no block named {}", name),
         }
     }
+
+    pub fn get_properties(self) -> &'static BlockProperties {
+        &BLOCKS[self]
+    }
+
+    pub fn get_name(self) -> &'static str {
+        &self.get_properties().name
+    }
+
+    pub fn can_pass_through(self) -> bool {
+        !self.get_properties().solid
+    }
+
+    pub fn as_item(self) -> Vec<Item> {
+        self.get_properties().drops.clone()
+    }
+
+    pub fn falls(self) -> bool {
+        self.get_properties().falls
+    }
+
+    pub fn get_hardness(self) -> f64 {
+        self.get_properties().hardness
+    }
+
+    pub fn get_light_loss(self) -> u8 {
+        self.get_properties().light_loss
+    }
+
+    pub fn get_light_emission(self) -> Light {
+        self.get_properties().light_emission
+    }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum NaturalBackground {
     Sky,
     Dirt,
 }
+
+impl NaturalBackground {
+    pub fn get_id(&self) -> u8 {
+        match self {
+            NaturalBackground::Sky => 0,
+            NaturalBackground::Dirt => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<NaturalBackground> {
+        match id {
+            0 => Some(NaturalBackground::Sky),
+            1 => Some(NaturalBackground::Dirt),
+            _ => None,
+        }
+    }
+}
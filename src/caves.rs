@@ -0,0 +1,106 @@
+//! Underground caves: winding tunnels following the zero lines of a noise, and caverns getting larger with depth.
+
+use crate::{
+    biomes::biome_at,
+    chunks::{surface_height, CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_SIZE},
+    noise::noise_2d_area,
+    seed::WorldSeed,
+};
+
+const TUNNEL_NOISE_LAYER: u32 = 100;
+const CAVERN_NOISE_LAYER: u32 = 101;
+
+/// Caves never get closer than this to the surface so that they don't open holes in the ground.
+const SURFACE_CRUST: isize = 5;
+/// Blocks computed on each side of a section so that pockets crossing its borders are seen entirely.
+const WINDOW_MARGIN: isize = 16;
+/// Smaller pockets are filled with dirt.
+/// A smaller pocket fits in a window margin, so every section it crosses makes the same choice.
+pub const MIN_CAVE_SIZE: usize = 16;
+
+/// Returns the blocks carved out by caves in a section, column by column.
+/// Like the terrain, it only depends on the seed and on the coordinates of the section.
+pub fn generate_caves(seed: WorldSeed, chunk_number: isize, section: usize) -> Vec<bool> {
+    let window_x = chunk_number * CHUNK_WIDTH as isize - WINDOW_MARGIN;
+    let window_y = (section * SECTION_SIZE) as isize - WINDOW_MARGIN;
+    let width = CHUNK_WIDTH + 2 * WINDOW_MARGIN as usize;
+    let height = SECTION_SIZE + 2 * WINDOW_MARGIN as usize;
+
+    let window = (window_x, window_y);
+    let tunnels = noise_2d_area(seed, TUNNEL_NOISE_LAYER, window, (width, height), (24.0, 16.0));
+    let caverns = noise_2d_area(seed, CAVERN_NOISE_LAYER, window, (width, height), (48.0, 32.0));
+    let cavern_details = noise_2d_area(seed, CAVERN_NOISE_LAYER + 1000, window, (width, height), (16.0, 12.0));
+
+    let mut carved = vec![false; width * height];
+    for column in 0..width {
+        let x = window_x + column as isize;
+        let surface = surface_height(seed, x).floor().clamp(1.0, 2047.0) as isize;
+        let density = biome_at(seed, x).get_cave_density();
+
+        let rows = (surface + SURFACE_CRUST).max(window_y)..(window_y + height as isize).min(CHUNK_HEIGHT as isize);
+        for y in rows {
+            let depth = (y - surface - SURFACE_CRUST) as f64;
+            let idx = (y - window_y) as usize * width + column;
+
+            // tunnels are thin near the surface and reach their full width 20 blocks deeper
+            let tunnel_width = 0.05 * density * (depth / 20.0).min(1.0);
+            // caverns are rare near the surface and common 500 blocks deeper
+            let cavern_threshold = 0.55 - 0.15 * (depth / 500.0).min(1.0);
+            let cavern = (caverns[idx] * 2.0 + cavern_details[idx]) / 3.0 * density;
+
+            carved[idx] = tunnels[idx].abs() < tunnel_width || cavern > cavern_threshold;
+        }
+    }
+
+    fill_small_pockets(&mut carved, width, height);
+
+    let margin = WINDOW_MARGIN as usize;
+    (margin..margin + CHUNK_WIDTH)
+        .flat_map(|column| (margin..margin + SECTION_SIZE).map(move |y| (column, y)))
+        .map(|(column, y)| carved[y * width + column])
+        .collect()
+}
+
+/// Fills the pockets smaller than `MIN_CAVE_SIZE` which don't touch the edges of the window.
+fn fill_small_pockets(carved: &mut [bool], width: usize, height: usize) {
+    let mut visited = vec![false; carved.len()];
+    let mut pocket = Vec::new();
+    for start in 0..carved.len() {
+        if !carved[start] || visited[start] {
+            continue;
+        }
+
+        pocket.clear();
+        pocket.push(start);
+        visited[start] = true;
+        let mut touches_edges = false;
+        let mut next = 0;
+        while next < pocket.len() {
+            let idx = pocket[next];
+            next += 1;
+            let (x, y) = (idx % width, idx / width);
+            if x == 0 || x == width - 1 || y == 0 || y == height - 1 {
+                touches_edges = true;
+            }
+
+            let neighbours = [
+                (x > 0, idx.wrapping_sub(1)),
+                (x + 1 < width, idx + 1),
+                (y > 0, idx.wrapping_sub(width)),
+                (y + 1 < height, idx + width),
+            ];
+            for (exists, neighbour) in neighbours.iter() {
+                if *exists && carved[*neighbour] && !visited[*neighbour] {
+                    visited[*neighbour] = true;
+                    pocket.push(*neighbour);
+                }
+            }
+        }
+
+        if !touches_edges && pocket.len() < MIN_CAVE_SIZE {
+            for idx in &pocket {
+                carved[*idx] = false;
+            }
+        }
+    }
+}
@@ -1,147 +1,300 @@
-use crate::{blocks::{NaturalBackground, BlockType, Block}, coords::x_to_biome};
-use arr_macro::arr;
-use std::hash::Hasher;
-use twox_hash::XxHash32;
-
-#[derive(Debug)]
-pub enum Biome {
-    Hills,
-    Grassland,
-    TemperateBroadleafForest,
+use crate::{
+    biomes::{biome_at, Biome},
+    blocks::{Block, BlockType, NaturalBackground},
+    caves::generate_caves,
+    fluid::{settled_level, Fluid, FLUID_FULL},
+    light::Light,
+    noise::{fractal_noise_1d, noise_1d, noise_2d_area},
+    seed::WorldSeed,
+    structures::structures_between,
+    trees::{trees_around, TreePart},
+    water::{aquifers_between, basin_at, basins_between, is_shore},
+};
+
+const TERRAIN_NOISE_LAYER: u32 = 0;
+const DIRT_NOISE_LAYER: u32 = 1;
+const ORE_NOISE_LAYER: u32 = 200;
+/// Biomes are blended with the ones this many columns away.
+const BLEND_RADIUS: isize = 32;
+const BLEND_STEP: isize = 8;
+
+/// Ores, and pockets of gravel, with the depth under the stone line where they start to appear and the noise value over which veins are placed.
+/// A higher threshold gives smaller and rarer veins.
+const ORES: [(&str, usize, f64); 4] = [
+    ("coal_ore", 0, 0.7),
+    ("iron_ore", 40, 0.78),
+    ("gold_ore", 150, 0.85),
+    ("gravel", 0, 0.8),
+];
+
+/// Height of the terrain at a column before biomes are blended.
+fn biome_surface_height(seed: WorldSeed, biome: Biome, x: isize) -> f64 {
+    let range = biome.get_height();
+    // the noise slope rarely goes over 2 so the amplitude keeps the terrain under the max slope of the biome
+    let frequency = biome.get_frequency() / 4.0;
+    let amplitude = biome.get_max_slope() / (frequency * 2.0);
+    (range.start + range.end) / 2.0 + fractal_noise_1d(seed, TERRAIN_NOISE_LAYER, x as f64 * frequency, 3) * amplitude
 }
 
-impl Biome {
-    fn get_frequency(&self) -> f64 {
-        match self {
-            Biome::Hills => 0.2,
-            Biome::Grassland => 0.06,
-            Biome::TemperateBroadleafForest => 0.08,
+/// Height of the terrain at a column, before lakes and rivers are carved.
+/// The heights of the biomes sampled every `BLEND_STEP` columns around `x` are averaged with weights decreasing to 0 at `BLEND_RADIUS`,
+/// so that the surface stays continuous across the borders of the biomes.
+pub fn terrain_height(seed: WorldSeed, x: isize) -> f64 {
+    let (mut height, mut total_weight) = (0.0, 0.0);
+    let first_sample = (x - BLEND_RADIUS).div_euclid(BLEND_STEP) * BLEND_STEP + BLEND_STEP;
+    for sample in (first_sample..x + BLEND_RADIUS).step_by(BLEND_STEP as usize) {
+        let weight = 1.0 - (x - sample).abs() as f64 / BLEND_RADIUS as f64;
+        height += biome_surface_height(seed, biome_at(seed, sample), x) * weight;
+        total_weight += weight;
+    }
+    height / total_weight
+}
+
+/// Height of the surface at a column, depending only on the seed and on `x`.
+pub fn surface_height(seed: WorldSeed, x: isize) -> f64 {
+    let terrain = terrain_height(seed, x);
+    terrain + basin_at(seed, x).map_or(0.0, |basin| basin.carving(seed, x, terrain))
+}
+
+/// The block at the surface of a column and the one filling the layer down to the stone:
+/// the ones of its biome, or sand on the shores of lakes and rivers.
+pub fn soil_at(seed: WorldSeed, x: isize) -> (&'static str, &'static str) {
+    match is_shore(seed, x) {
+        true => ("sand", "sand"),
+        false => biome_at(seed, x).get_soil(),
+    }
+}
+
+/// Caves this far under the surface have a floor of lava.
+const LAVA_DEPTH: usize = 150;
+
+pub const CHUNK_WIDTH: usize = 32;
+pub const CHUNK_HEIGHT: usize = 2048;
+/// Chunks are cut vertically into square sections which are only generated when the player comes close.
+pub const SECTION_SIZE: usize = 32;
+pub const SECTION_COUNT: usize = CHUNK_HEIGHT / SECTION_SIZE;
+
+/// A 32×32 part of a chunk, stored column by column.
+/// Sections made of a single block, like the sky or solid stone, don't allocate anything.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Section {
+    Ungenerated,
+    Uniform(Block),
+    Full(Vec<Block>),
+}
+
+impl Section {
+    /// Makes a section from its blocks, column by column.
+    pub fn from_blocks(blocks: Vec<Block>) -> Section {
+        match blocks.first() {
+            Some(first) if blocks.iter().all(|block| block == first) => Section::Uniform(*first),
+            _ => Section::Full(blocks),
         }
     }
 
-    fn get_max_slope(&self) -> f64 {
+    pub fn is_generated(&self) -> bool {
+        !matches!(self, Section::Ungenerated)
+    }
+
+    fn get(&self, x: usize, y: usize) -> Option<&Block> {
         match self {
-            Biome::Hills => 0.9,
-            Biome::Grassland => 0.5,
-            Biome::TemperateBroadleafForest => 0.7,
+            Section::Ungenerated => None,
+            Section::Uniform(block) => Some(block),
+            Section::Full(blocks) => Some(&blocks[x * SECTION_SIZE + y]),
         }
     }
 
-    fn get_height(&self) -> std::ops::Range<f64> {
+    /// Uniform sections get their own blocks as soon as one of them can be modified.
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Block> {
+        if let Section::Uniform(block) = self {
+            *self = Section::Full(vec![*block; SECTION_SIZE * SECTION_SIZE]);
+        }
         match self {
-            Biome::Hills => (30.0..40.0),
-            Biome::Grassland => (30.0..40.0),
-            Biome::TemperateBroadleafForest => (30.0..40.0),
+            Section::Full(blocks) => Some(&mut blocks[x * SECTION_SIZE + y]),
+            _ => None,
         }
     }
 
-    fn get_tree_prob(&self) -> u16 {
-        match self {
-            Biome::Hills => 50,
-            Biome::Grassland => 32,
-            Biome::TemperateBroadleafForest => 10,
+    /// Generates a section from scratch. The same seed and coordinates always give the same section.
+    pub fn generate(seed: WorldSeed, chunk_number: isize, section: usize) -> Section {
+        let stone = BlockType::from_name("stone");
+        let ore_types: Vec<BlockType> = ORES.iter().map(|(name, _, _)| BlockType::from_name(name)).collect();
+
+        let top = section * SECTION_SIZE;
+        let mut caves = generate_caves(seed, chunk_number, section);
+        let ores: Vec<Vec<f64>> = (0..ORES.len())
+            .map(|idx| {
+                noise_2d_area(
+                    seed,
+                    ORE_NOISE_LAYER + idx as u32,
+                    (chunk_number * 32, top as isize),
+                    (CHUNK_WIDTH, SECTION_SIZE),
+                    (5.0, 4.0),
+                )
+            })
+            .collect();
+        let structures: Vec<_> = structures_between(seed, chunk_number * 32, chunk_number * 32 + 32)
+            .into_iter()
+            .filter(|structure| structure.rows().start < (top + SECTION_SIZE) as isize && structure.rows().end > top as isize)
+            .collect();
+        let basins: Vec<_> = basins_between(seed, chunk_number * 32, chunk_number * 32 + 32)
+            .into_iter()
+            .map(|basin| (basin, basin.water_level(seed)))
+            .collect();
+        let aquifers = aquifers_between(seed, chunk_number * 32, chunk_number * 32 + 32);
+        for (x, column_idx) in (chunk_number * 32..chunk_number * 32 + 32).zip(0..) {
+            for (y, row) in (top as isize..(top + SECTION_SIZE) as isize).zip(0..) {
+                if let Some(carved) = aquifers.iter().find_map(|aquifer| aquifer.carves((x, y))) {
+                    caves[column_idx * SECTION_SIZE + row] = carved;
+                }
+            }
         }
+
+        let mut blocks = Vec::with_capacity(CHUNK_WIDTH * SECTION_SIZE);
+        for (x, column_idx) in (chunk_number * 32..chunk_number * 32 + 32).zip(0..) {
+            let height = surface_height(seed, x).floor().clamp(1.0, 2047.0) as usize;
+            let (surface, soil) = soil_at(seed, x);
+            let (surface, soil) = (BlockType::from_name(surface), BlockType::from_name(soil));
+            // only sections above the ground can hold parts of trees
+            let trees = match top < height {
+                true => trees_around(seed, x),
+                false => Vec::new(),
+            };
+            let water_level = basins
+                .iter()
+                .find(|(basin, _)| basin.columns().contains(&x))
+                .map(|(_, level)| *level as usize);
+            // the dirt layer is between 6 and 14 blocks thick
+            let stone_line = (height as f64 + 10.0 + noise_1d(seed, DIRT_NOISE_LAYER, x as f64 / 16.0) * 4.0) as usize;
+
+            for (y, row) in (top..top + SECTION_SIZE).zip(0..) {
+                let mut block = Block {
+                    block_type: soil,
+                    natural_background: NaturalBackground::Dirt,
+                    light: Light::BLACK,
+                    fluid: Fluid::Water,
+                    fluid_level: 0,
+                };
+                if y < height {
+                    block.block_type = trees
+                        .iter()
+                        .find_map(|tree| tree.get((x, y as isize)))
+                        .map_or(BlockType::AIR, TreePart::get_block_type);
+                    block.natural_background = NaturalBackground::Sky;
+                    // lakes and rivers fill the air of their basin under the water level
+                    if let Some(level) = water_level.filter(|level| y >= *level && block.block_type == BlockType::AIR) {
+                        block.fluid_level = settled_level(y - level);
+                    }
+                } else if y == height {
+                    block.block_type = surface;
+                } else if caves[column_idx * SECTION_SIZE + row] {
+                    block.block_type = BlockType::AIR;
+                    // the floors of deep caves hold lava, but the bottom of the section is left dry since the cave may go on in the next one
+                    if let Some(aquifer) = aquifers.iter().find(|aquifer| aquifer.floods((x, y as isize))) {
+                        block.fluid_level = settled_level((y as isize - aquifer.top) as usize);
+                    } else if y >= height + LAVA_DEPTH && row + 1 < SECTION_SIZE && !caves[column_idx * SECTION_SIZE + row + 1] {
+                        block.fluid = Fluid::Lava;
+                        block.fluid_level = FLUID_FULL;
+                    }
+                } else if y >= stone_line {
+                    block.block_type = stone;
+                    for (((_, min_depth, threshold), noise), ore) in ORES.iter().zip(ores.iter()).zip(ore_types.iter()) {
+                        if y >= stone_line + min_depth && noise[row * CHUNK_WIDTH + column_idx] > *threshold {
+                            block.block_type = *ore;
+                        }
+                    }
+                }
+                // structures replace the terrain and the fluids, except where their prefab keeps them
+                if let Some(block_type) = structures.iter().find_map(|structure| structure.get((x, y as isize))) {
+                    block.block_type = block_type;
+                    block.fluid = Fluid::Water;
+                    block.fluid_level = 0;
+                }
+                blocks.push(block);
+            }
+        }
+
+        Section::from_blocks(blocks)
     }
 }
 
+/// A 32 blocks wide slice of the world, made of `SECTION_COUNT` sections from top to bottom.
 pub struct Chunk {
-    pub blocks: Vec<[Block; 2048]>,   // 32
-    pub left_config: (f64, f64),  // (height, slope)
-    pub right_config: (f64, f64), // idem
+    sections: Vec<Section>,
 }
 
 impl Chunk {
-    #[allow(clippy::cognitive_complexity)]
-    #[allow(clippy::eval_order_dependence)]
-    pub fn generate(height: &mut f64, slope: &mut f64, left_to_right: bool, mut x: isize) -> Chunk {
-        let begin_config: (f64, f64) = (*height, *slope);
-        let biome = x_to_biome(x);
-
-        let mut blocks = Vec::new();
-        for _idx in 0..32 {
-            let mut hasher = XxHash32::with_seed(42);
-            hasher.write_isize(x);
-            let hash = hasher.finish();
-
-            let mut random: f64 = hash as f64 - 2_147_483_647.0;
-            random /= 2_147_483_647.0;
-            *slope += random * biome.get_frequency();
-
-            if *slope > biome.get_max_slope() {
-                *slope = biome.get_max_slope();
-            } else if *slope < -biome.get_max_slope() {
-                *slope = -biome.get_max_slope();
-            }
+    /// A chunk of which no section is generated yet.
+    pub fn new() -> Chunk {
+        Chunk {
+            sections: vec![Section::Ungenerated; SECTION_COUNT],
+        }
+    }
 
-            if (*height < biome.get_height().start - 10.0 && *slope < -0.4)
-                || *height < biome.get_height().start
-            {
-                *slope += biome.get_frequency() / 3.0;
-            }
-            if (*height > biome.get_height().end + 10.0 && *slope > 0.4)
-                || *height > biome.get_height().end
-            {
-                *slope -= biome.get_frequency() / 3.0;
-            }
+    /// Generates all the sections of a chunk.
+    pub fn generate(seed: WorldSeed, chunk_number: isize) -> Chunk {
+        let mut chunk = Chunk::new();
+        for section in 0..SECTION_COUNT {
+            chunk.generate_section(seed, chunk_number, section);
+        }
+        chunk
+    }
 
-            let hasher2 = XxHash32::with_seed(42); // to avoid generating a tree if there is a tree at the left // does not work
-            hasher.write_isize(x - 1);
-            let tree = hash % biome.get_tree_prob() as u64 == 0
-                && hasher2.finish() % biome.get_tree_prob() as u64 != 0;
+    pub fn generate_section(&mut self, seed: WorldSeed, chunk_number: isize, section: usize) {
+        self.sections[section] = Section::generate(seed, chunk_number, section);
+    }
 
-            *height += *slope;
+    pub fn filled(block: Block) -> Chunk {
+        Chunk {
+            sections: vec![Section::Uniform(block); SECTION_COUNT],
+        }
+    }
 
-            if left_to_right {
-                x += 1;
-            } else {
-                x -= 1;
-            }
+    pub fn section(&self, section: usize) -> &Section {
+        &self.sections[section]
+    }
 
-            let mut column = arr!(Block{block_type: BlockType::Dirt, natural_background: NaturalBackground::Dirt, light: 0, water: 0.0}; 2048);
-            for block in column.iter_mut().take(height.floor() as usize) {
-                *block = Block {
-                    block_type: BlockType::Air,
-                    natural_background: NaturalBackground::Sky,
-                    light: 0,
-                    water: 0.0,
-                };
-            }
-            column[height.floor() as usize] = Block {
-                block_type: BlockType::Grass,
-                natural_background: NaturalBackground::Dirt,
-                light: 0,
-                water: 0.0,
-            };
-            if tree && height.floor() as usize > 0 {
-                column[height.floor() as usize - 1] = Block {
-                    block_type: BlockType::Tree,
-                    natural_background: NaturalBackground::Dirt,
-                    light: 0,
-                    water: 0.0,
-                };
-            }
+    pub fn set_section(&mut self, section: usize, content: Section) {
+        self.sections[section] = content;
+    }
+
+    pub fn is_generated(&self, section: usize) -> bool {
+        self.sections.get(section).is_some_and(Section::is_generated)
+    }
 
-            column[1].water = 16.0;
+    /// Bytes allocated on the heap by the chunk. Uniform and ungenerated sections only take their place in the list of sections.
+    pub fn heap_size(&self) -> usize {
+        let blocks: usize = self
+            .sections
+            .iter()
+            .map(|section| match section {
+                Section::Full(blocks) => blocks.capacity() * std::mem::size_of::<Block>(),
+                _ => 0,
+            })
+            .sum();
+        self.sections.capacity() * std::mem::size_of::<Section>() + blocks
+    }
 
-            blocks.push(column)
+    /// Returns `None` when the coordinates are outside of the chunk or in a section which is not generated.
+    pub fn get(&self, x: usize, y: usize) -> Option<&Block> {
+        if x < CHUNK_WIDTH && y < CHUNK_HEIGHT {
+            self.sections[y / SECTION_SIZE].get(x, y % SECTION_SIZE)
+        } else {
+            None
         }
+    }
 
-        if !left_to_right {
-            blocks.reverse();
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Block> {
+        if x < CHUNK_WIDTH && y < CHUNK_HEIGHT {
+            self.sections[y / SECTION_SIZE].get_mut(x, y % SECTION_SIZE)
+        } else {
+            None
         }
+    }
+}
 
-        Chunk {
-            blocks,
-            left_config: if left_to_right {
-                begin_config
-            } else {
-                (*height, *slope)
-            },
-            right_config: if !left_to_right {
-                begin_config
-            } else {
-                (*height, *slope)
-            },
-        }
+impl Default for Chunk {
+    fn default() -> Chunk {
+        Chunk::new()
     }
 }
@@ -0,0 +1,93 @@
+//! Time of the day.
+//!
+//! The clock counts ticks, `TICKS_PER_SECOND` of them whatever the frame rate. A day starts at midnight, the sun rises during `SUNRISE`,
+//! shines until it sets during `SUNSET` and the night lasts until the next day.
+
+use crate::light::MAX_LIGHT;
+
+pub const TICKS_PER_SECOND: f64 = 60.0;
+/// Ten minutes.
+pub const DEFAULT_DAY_LENGTH: u64 = 36_000;
+/// Parts of the day during which daylight rises and falls.
+pub const SUNRISE: (f64, f64) = (0.2, 0.3);
+pub const SUNSET: (f64, f64) = (0.7, 0.8);
+/// How much of the sunlight remains at night, so that the surface is still visible.
+pub const NIGHT_LIGHT: u8 = 20;
+/// Daylight only changes by steps of this level so that the map is not rendered again on every tick.
+const DAYLIGHT_STEP: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clock {
+    /// Ticks since the world was created.
+    pub ticks: u64,
+    /// Ticks in a day.
+    pub day_length: u64,
+    /// Part of a tick elapsed since the last one.
+    remainder: f64,
+}
+
+impl Clock {
+    /// A clock at the end of the sunrise of the first day.
+    pub fn new(day_length: u64) -> Clock {
+        Clock {
+            ticks: (day_length as f64 * SUNRISE.1) as u64,
+            day_length,
+            remainder: 0.0,
+        }
+    }
+
+    /// A clock which already ran for some ticks, like one restored from a save.
+    pub fn with_ticks(ticks: u64, day_length: u64) -> Clock {
+        Clock {
+            ticks,
+            ..Clock::new(day_length)
+        }
+    }
+
+    /// Runs the ticks which fit in `elapsed` seconds, with the time left from the previous calls.
+    pub fn update(&mut self, elapsed: f64) {
+        self.remainder += elapsed.max(0.0) * TICKS_PER_SECOND;
+        let ticks = self.remainder.floor();
+        self.remainder -= ticks;
+        self.ticks += ticks as u64;
+    }
+
+    pub fn advance(&mut self, ticks: u64) {
+        self.ticks += ticks;
+    }
+
+    pub fn day(&self) -> u64 {
+        self.ticks / self.day_length
+    }
+
+    /// Part of the current day which is elapsed, 0 at midnight and 0.5 at noon.
+    pub fn time_of_day(&self) -> f64 {
+        (self.ticks % self.day_length) as f64 / self.day_length as f64
+    }
+
+    /// How much the sun shines, from 0 at night to 1 during the day.
+    pub fn sun_height(&self) -> f64 {
+        let time = self.time_of_day();
+        if time < SUNRISE.0 || time >= SUNSET.1 {
+            0.0
+        } else if time < SUNRISE.1 {
+            (time - SUNRISE.0) / (SUNRISE.1 - SUNRISE.0)
+        } else if time < SUNSET.0 {
+            1.0
+        } else {
+            (SUNSET.1 - time) / (SUNSET.1 - SUNSET.0)
+        }
+    }
+
+    /// The level to which sunlight is scaled, between `NIGHT_LIGHT` and `MAX_LIGHT`.
+    pub fn daylight(&self) -> u8 {
+        let level = NIGHT_LIGHT as f64 + (MAX_LIGHT - NIGHT_LIGHT) as f64 * self.sun_height();
+        (level / DAYLIGHT_STEP as f64).round() as u8 * DAYLIGHT_STEP
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::new(DEFAULT_DAY_LENGTH)
+    }
+}
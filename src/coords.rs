@@ -1,7 +1,4 @@
-use crate::{chunks::Biome, player::Player};
-use std::hash::Hasher;
-use twox_hash::XxHash32;
-use wasm_game_lib::elog;
+use crate::player::Player;
 
 pub fn screen_to_map(
     x: f64,
@@ -48,22 +45,3 @@ pub fn x_to_chunk_and_column(x: isize) -> (isize, isize) {
 
     (chunk_number, column_index)
 }
-
-pub fn x_to_biome(x: isize) -> Biome {
-    let mut chunk = x_to_chunk(x);
-    chunk -= chunk % 8;
-    chunk /= 8;
-    let mut hasher = XxHash32::with_seed(42);
-    hasher.write_isize(chunk);
-    let hash = hasher.finish();
-
-    match hash % 3 {
-        0 => Biome::Hills,
-        1 => Biome::Grassland,
-        2 => Biome::TemperateBroadleafForest,
-        i => {
-            elog!("ERROR! Pattern {} not covered in x_to_biome function", i);
-            Biome::Hills
-        }
-    }
-}
@@ -0,0 +1,221 @@
+//! Maps of generated regions with one pixel per block, to look at the generation without playing.
+//!
+//! The `worldmap` tool writes them as PNG files, for example `cargo run --release --bin worldmap -- 42 -16..16 --rows 0..512`.
+//! Each block gets the average color of its texture, and a band at the top of the map shows the biome of each column.
+
+use crate::{
+    biomes::biome_at,
+    blocks::{BlockTexture, BlockType, NaturalBackground, BLOCKS},
+    chunks::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_SIZE},
+    fluid::{Fluid, FLUID_FULL},
+    light::MAX_LIGHT,
+    seed::WorldSeed,
+    software_renderer::{RgbaImage, SoftwareTextures},
+    textures::Sprite,
+    world::World,
+};
+use std::ops::Range;
+
+pub const USAGE: &str = "usage: worldmap <seed> <first chunk>..<last chunk> [--rows <first row>..<last row>] [--output <file>] [--settle] [--no-light] [--no-biomes]
+
+  --rows        rows to export, 0..256 by default
+  --output      file to write, map.png by default
+  --settle      runs the fluids until they stop flowing
+  --no-light    shows every block fully lit
+  --no-biomes   removes the band showing the biomes";
+
+/// Rows of pixels of the band showing the biomes over the map.
+pub const BIOME_BAND_HEIGHT: u32 = 4;
+/// Fluid ticks run at most when settling the fluids. Pools of lava take tens of thousands of ticks to even their levels.
+pub const MAX_SETTLING_TICKS: usize = 200_000;
+
+const SKY_COLOR: [u8; 3] = [135, 206, 235];
+const WATER_COLOR: [u8; 3] = [0, 0, 255];
+const LAVA_COLOR: [u8; 3] = [255, 100, 0];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportOptions {
+    pub seed: WorldSeed,
+    /// Chunks to export, the last one excluded.
+    pub chunks: Range<isize>,
+    /// Rows to export, the last one excluded.
+    pub rows: Range<usize>,
+    pub output: String,
+    pub settle_fluids: bool,
+    pub lighting: bool,
+    pub biome_band: bool,
+}
+
+fn parse_range<T: std::str::FromStr + PartialOrd>(text: &str) -> Result<Range<T>, String> {
+    let (start, end) = text.split_once("..").ok_or_else(|| format!("{} is not a range like 0..10", text))?;
+    match (start.trim().parse(), end.trim().parse()) {
+        (Ok(start), Ok(end)) if start < end => Ok(start..end),
+        (Ok(_), Ok(_)) => Err(format!("the range {} is empty", text)),
+        _ => Err(format!("{} is not a range like 0..10", text)),
+    }
+}
+
+impl ExportOptions {
+    /// Reads the arguments of the `worldmap` tool, without the name of the program.
+    pub fn parse(args: &[String]) -> Result<ExportOptions, String> {
+        let mut positional = Vec::new();
+        let mut options = ExportOptions {
+            seed: WorldSeed::default(),
+            chunks: 0..0,
+            rows: 0..256,
+            output: String::from("map.png"),
+            settle_fluids: false,
+            lighting: true,
+            biome_band: true,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--rows" => options.rows = parse_range(args.next().ok_or("--rows needs a range")?)?,
+                "--output" => options.output = args.next().ok_or("--output needs a file")?.clone(),
+                "--settle" => options.settle_fluids = true,
+                "--no-light" => options.lighting = false,
+                "--no-biomes" => options.biome_band = false,
+                // negative chunk numbers look like flags but are ranges
+                flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+                _ => positional.push(arg),
+            }
+        }
+
+        match positional.as_slice() {
+            [seed, chunks] => {
+                options.seed = WorldSeed::parse(seed);
+                options.chunks = parse_range(chunks)?;
+            }
+            _ => return Err(String::from("expected a seed and a range of chunks")),
+        }
+        if options.rows.end > CHUNK_HEIGHT {
+            return Err(format!("the world is only {} rows high", CHUNK_HEIGHT));
+        }
+        Ok(options)
+    }
+}
+
+/// Generates the chunks of the exported region. Every section from the top of the world to the last row is generated
+/// so that sunlight comes down to the exported rows.
+pub fn generate_region(options: &ExportOptions) -> World {
+    let last_section = (options.rows.end - 1) / SECTION_SIZE;
+    let chunks = options
+        .chunks
+        .clone()
+        .map(|chunk_number| {
+            let mut chunk = Chunk::new();
+            for section in 0..=last_section {
+                chunk.generate_section(options.seed, chunk_number, section);
+            }
+            chunk
+        })
+        .collect();
+    let mut world = World::from_chunks(options.seed, options.chunks.start, chunks);
+
+    if options.settle_fluids {
+        for _ in 0..MAX_SETTLING_TICKS {
+            if world.are_fluids_settled() {
+                break;
+            }
+            world.tick_fluids();
+            // nothing renders the blocks moved by the fluids
+            world.blocks_to_render.clear();
+            world.light_to_render.clear();
+        }
+    }
+    world
+}
+
+/// The average color of the opaque pixels of a part of an image, `None` if they are all transparent.
+fn average_color(image: &RgbaImage, (x, y): (u32, u32), (width, height): (u32, u32)) -> Option<[u8; 3]> {
+    let (mut total, mut count) = ([0u64; 3], 0);
+    for y in y..(y + height).min(image.height) {
+        for x in x..(x + width).min(image.width) {
+            let pixel = image.get_pixel(x, y);
+            if pixel[3] >= 128 {
+                for channel in 0..3 {
+                    total[channel] += pixel[channel] as u64;
+                }
+                count += 1;
+            }
+        }
+    }
+    match count {
+        0 => None,
+        _ => Some(total.map(|sum| (sum / count) as u8)),
+    }
+}
+
+/// The colors of the blocks on the map, indexed by block type.
+/// Blocks without texture, like the air, are `None` and show their background.
+pub fn block_colors(textures: &SoftwareTextures) -> Vec<Option<[u8; 3]>> {
+    (0..BLOCKS.len())
+        .map(|idx| match &BlockType(idx as u16).get_properties().texture {
+            None => None,
+            // the first tile is the one of a block surrounded by solid blocks
+            Some(BlockTexture::Atlas { atlas }) => {
+                average_color(textures.get(Sprite::Blocks), (atlas.0 as u32 * 16, atlas.1 as u32 * 16), (16, 16))
+            }
+            Some(BlockTexture::Sprite { sprite, .. }) => {
+                let image = textures.get(*sprite);
+                average_color(image, (0, 0), (image.width, image.height))
+            }
+        })
+        .collect()
+}
+
+/// Color of the background of the caves, darker than the dirt blocks so that they stand out when lighting is off.
+fn cave_color(textures: &SoftwareTextures) -> [u8; 3] {
+    let image = textures.get(Sprite::BackgroundDirt);
+    average_color(image, (0, 0), (image.width, image.height)).unwrap_or([60, 40, 30]).map(|channel| channel / 2)
+}
+
+fn mix(color: [u8; 3], over: [u8; 3], opacity: f64) -> [u8; 3] {
+    [0, 1, 2].map(|channel| (color[channel] as f64 * (1.0 - opacity) + over[channel] as f64 * opacity).round() as u8)
+}
+
+/// Draws the map of a generated region, one pixel per block, with the band of the biomes on top of it if enabled.
+pub fn render_region(world: &World, options: &ExportOptions, textures: &SoftwareTextures) -> RgbaImage {
+    let colors = block_colors(textures);
+    let cave = cave_color(textures);
+    let band_height = if options.biome_band { BIOME_BAND_HEIGHT } else { 0 };
+    let first_column = options.chunks.start * CHUNK_WIDTH as isize;
+    let width = (options.chunks.end - options.chunks.start) as u32 * CHUNK_WIDTH as u32;
+    let height = band_height + options.rows.len() as u32;
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in 0..height {
+        for x in first_column..first_column + width as isize {
+            let color = match row.checked_sub(band_height) {
+                None => biome_at(options.seed, x).get_map_color(),
+                Some(row) => {
+                    let position = (x, (options.rows.start + row as usize) as isize);
+                    let block = world[position];
+                    let mut color = match (colors[block.block_type.0 as usize], block.natural_background) {
+                        (Some(color), _) => color,
+                        (None, NaturalBackground::Sky) => SKY_COLOR,
+                        (None, NaturalBackground::Dirt) => cave,
+                    };
+                    if block.fluid_level > 0 {
+                        let fluid_color = match block.fluid {
+                            Fluid::Water => WATER_COLOR,
+                            Fluid::Lava => LAVA_COLOR,
+                        };
+                        color = mix(color, fluid_color, 0.75 * block.fluid_level.min(FLUID_FULL) as f64 / FLUID_FULL as f64);
+                    }
+                    if options.lighting {
+                        let light = world.get_light_color(position);
+                        color = [0, 1, 2].map(|channel| {
+                            (color[channel] as usize * light[channel].min(MAX_LIGHT) as usize / MAX_LIGHT as usize) as u8
+                        });
+                    }
+                    color
+                }
+            };
+            pixels.extend_from_slice(&[color[0], color[1], color[2], 255]);
+        }
+    }
+
+    RgbaImage { width, height, pixels }
+}
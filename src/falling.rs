@@ -0,0 +1,107 @@
+//! Blocks affected by gravity.
+//!
+//! A block which falls, like sand, leaves the grid as soon as the block under it is air
+//! and is placed back where it lands.
+
+use crate::{
+    blocks::{Block, BlockType},
+    world::World,
+};
+
+/// Speed gained on each frame, in blocks per frame.
+const GRAVITY: f64 = 0.02;
+const MAX_SPEED: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FallingBlock {
+    pub block_type: BlockType,
+    pub x: isize,
+    /// Top of the block, in blocks.
+    pub y: f64,
+    pub speed: f64,
+}
+
+impl World {
+    /// A falling block can only go down through air which is generated.
+    fn can_fall_into(&self, position: (isize, isize)) -> bool {
+        self.is_generated(position) && self[position].block_type == BlockType::AIR
+    }
+
+    /// Makes the block at `position` fall if nothing holds it, and then the blocks piled on it.
+    pub fn check_support(&mut self, (x, mut y): (isize, isize)) {
+        while self.is_generated((x, y)) && self[(x, y)].block_type.falls() && self.can_fall_into((x, y + 1)) {
+            let block_type = self[(x, y)].block_type;
+            self.index_mut_and_render((x, y)).block_type = BlockType::AIR;
+            self.update_light((x, y));
+            self.wake_fluid((x, y));
+            self.falling_blocks.push(FallingBlock {
+                block_type,
+                x,
+                y: y as f64,
+                speed: 0.0,
+            });
+            y -= 1;
+        }
+    }
+
+    /// Pushes the fluid out of a block where a falling block lands, to the block above it or else to its sides.
+    /// Returns false if no neighbour can hold it.
+    fn displace_fluid(&mut self, (x, y): (isize, isize)) -> bool {
+        let Block { fluid, fluid_level, .. } = self[(x, y)];
+        if fluid_level == 0 {
+            return true;
+        }
+        match [(x, y - 1), (x - 1, y), (x + 1, y)].iter().copied().find(|side| self.can_hold_fluid(*side, fluid)) {
+            Some(side) => {
+                let block = self.index_mut_and_render(side);
+                block.fluid = fluid;
+                block.fluid_level = block.fluid_level.saturating_add(fluid_level);
+                self[(x, y)].fluid_level = 0;
+                self.wake_fluid(side);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the falling blocks and places the ones which land.
+    pub fn update_falling_blocks(&mut self) {
+        let mut landed = Vec::new();
+        let mut falling_blocks = std::mem::take(&mut self.falling_blocks);
+        falling_blocks.retain_mut(|block| {
+            let row = block.y.floor() as isize;
+            if !self.can_fall_into((block.x, row + 1)) {
+                landed.push((block.block_type, (block.x, row)));
+                return false;
+            }
+            // the speed stays under a block per frame so that the row below is always checked
+            block.speed = (block.speed + GRAVITY).min(MAX_SPEED);
+            block.y += block.speed;
+            true
+        });
+        self.falling_blocks = falling_blocks;
+
+        for (block_type, (x, y)) in landed {
+            // something may have been placed there in the meantime, so the block goes on the first air above it
+            let free_row = (0..=y)
+                .rev()
+                .take_while(|row| self.is_generated((x, *row)))
+                .find(|row| self[(x, *row)].block_type == BlockType::AIR);
+            match free_row {
+                Some(y) if self.displace_fluid((x, y)) => {
+                    self.index_mut_and_render((x, y)).block_type = block_type;
+                    self.update_light((x, y));
+                    self.wake_fluid((x, y));
+                }
+                // the column is full up to the top of the world or to a section which is not generated,
+                // or the fluid where it lands has nowhere to go, the block waits for some room
+                _ => self.falling_blocks.push(FallingBlock {
+                    block_type,
+                    x,
+                    y: y as f64,
+                    speed: 0.0,
+                }),
+            }
+        }
+    }
+}
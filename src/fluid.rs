@@ -0,0 +1,233 @@
+//! Fluid simulation.
+//!
+//! Fluids are cellular automata on integer levels, `FLUID_FULL` being a full block.
+//! On each tick, every active block computes how much fluid it gives to its neighbours from the levels of the previous tick,
+//! so that the result does not depend on the order in which blocks are visited:
+//! fluid falls first, then spreads evenly to the left and to the right, then goes up if the block is under pressure.
+//! A block holds up to `COMPRESSION` more than the one above it, which is what lets both sides of a U-tube reach the same height.
+//! Blocks stop being simulated once nothing changes around them.
+//!
+//! A block holds a single fluid. Lava only moves every `LAVA_SLOWNESS` ticks and cools down into stone where it touches water.
+
+use crate::{blocks::BlockType, light::Light, world::World};
+use std::collections::{BTreeSet, HashMap};
+
+/// The level of a full block of fluid.
+pub const FLUID_FULL: u16 = 1024;
+/// How much more fluid a block can hold than the block above it.
+const COMPRESSION: i32 = 32;
+/// How much fluid can move from a block to another in a tick.
+const MAX_FLOW: i32 = FLUID_FULL as i32;
+pub const FLUID_TICKS_PER_SECOND: f64 = 20.0;
+/// Ticks run at most by `update_fluids` so that a slow frame doesn't make the next ones slower.
+const MAX_TICKS_PER_UPDATE: u32 = 4;
+/// Lava only moves on one tick out of this many.
+pub const LAVA_SLOWNESS: u64 = 4;
+/// Lava thinner than this doesn't glow.
+const GLOWING_LEVEL: u16 = FLUID_FULL / 8;
+
+/// The fluid in a block. Empty blocks hold water so that they are all equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fluid {
+    Water,
+    Lava,
+}
+
+impl Fluid {
+    pub fn get_id(self) -> u8 {
+        match self {
+            Fluid::Water => 0,
+            Fluid::Lava => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Fluid> {
+        match id {
+            0 => Some(Fluid::Water),
+            1 => Some(Fluid::Lava),
+            _ => None,
+        }
+    }
+
+    /// Light emitted by a block holding `level` of this fluid.
+    pub fn get_light_emission(self, level: u16) -> Light {
+        match self {
+            Fluid::Lava if level >= GLOWING_LEVEL => Light::new(100, 55, 15),
+            _ => Light::BLACK,
+        }
+    }
+}
+
+/// How much of the fluid of two blocks on top of each other is in the bottom one when they are stable.
+fn stable_bottom_level(total: i32) -> i32 {
+    let full = FLUID_FULL as i32;
+    if total <= full {
+        total
+    } else if total < 2 * full + COMPRESSION {
+        (full * full + total * COMPRESSION) / (full + COMPRESSION)
+    } else {
+        (total + COMPRESSION) / 2
+    }
+}
+
+/// Level of a block of still fluid `depth` blocks under its surface.
+/// Each block holds `COMPRESSION` more than the one above it, so that generated water doesn't move until something changes around it.
+pub fn settled_level(depth: usize) -> u16 {
+    (FLUID_FULL as usize + depth * COMPRESSION as usize).min(u16::MAX as usize) as u16
+}
+
+/// Fluid given to a neighbour on the side. A difference of 1 is left so that levels settle.
+fn lateral_flow(level: i32, neighbour: i32) -> i32 {
+    ((level - neighbour + 2) / 4).max(0)
+}
+
+fn neighbours((x, y): (isize, isize)) -> [(isize, isize); 4] {
+    [(x + 1, y), (x - 1, y), (x, y - 1), (x, y + 1)]
+}
+
+impl World {
+    /// Fluid can only flow into air which is generated, so that it isn't lost in sections which don't exist yet,
+    /// and which doesn't hold another fluid.
+    pub fn can_hold_fluid(&self, position: (isize, isize), fluid: Fluid) -> bool {
+        self.is_generated(position)
+            && self[position].block_type == BlockType::AIR
+            && (self[position].fluid_level == 0 || self[position].fluid == fluid)
+    }
+
+    /// Simulates the fluids again around a block which changed. Fluid in a block which can no longer hold it is removed.
+    pub fn wake_fluid(&mut self, position: (isize, isize)) {
+        if self.is_generated(position) && self[position].block_type != BlockType::AIR {
+            self[position].fluid = Fluid::Water;
+            self[position].fluid_level = 0;
+        }
+        self.fluid_update.insert(position);
+        self.fluid_update.extend(neighbours(position));
+    }
+
+    /// Whether no fluid is moving anymore.
+    pub fn are_fluids_settled(&self) -> bool {
+        self.fluid_update.is_empty()
+    }
+
+    /// Runs the ticks which fit in `elapsed` seconds, with the time left from the previous calls.
+    pub fn update_fluids(&mut self, elapsed: f64) {
+        self.fluid_time += elapsed;
+        let mut ticks = 0;
+        while self.fluid_time >= 1.0 / FLUID_TICKS_PER_SECOND {
+            self.fluid_time -= 1.0 / FLUID_TICKS_PER_SECOND;
+            if ticks < MAX_TICKS_PER_UPDATE {
+                self.tick_fluids();
+                ticks += 1;
+            }
+        }
+    }
+
+    /// Moves the fluids of the active blocks once.
+    pub fn tick_fluids(&mut self) {
+        self.fluid_ticks += 1;
+        let lava_moves = self.fluid_ticks.is_multiple_of(LAVA_SLOWNESS);
+        // lava which waits for its turn stays active
+        let mut active = BTreeSet::new();
+        let mut cooling = Vec::new();
+        // changes of the level of each fluid, indexed by id
+        let mut changes: HashMap<(isize, isize), [i32; 2]> = HashMap::new();
+        let mut transfer = |fluid: Fluid, from: (isize, isize), to: (isize, isize), quantity: i32| {
+            changes.entry(from).or_insert([0; 2])[fluid.get_id() as usize] -= quantity;
+            changes.entry(to).or_insert([0; 2])[fluid.get_id() as usize] += quantity;
+        };
+
+        for (x, y) in std::mem::take(&mut self.fluid_update) {
+            if !self.is_generated((x, y)) || self[(x, y)].fluid_level == 0 {
+                continue;
+            }
+            let fluid = self[(x, y)].fluid;
+            if fluid == Fluid::Lava {
+                let touches_water = neighbours((x, y)).iter().any(|&neighbour| {
+                    self.is_generated(neighbour) && self[neighbour].fluid == Fluid::Water && self[neighbour].fluid_level > 0
+                });
+                if touches_water {
+                    cooling.push((x, y));
+                    continue;
+                }
+                if !lava_moves {
+                    active.insert((x, y));
+                    continue;
+                }
+            }
+            let mut remaining = self[(x, y)].fluid_level as i32;
+
+            if self.can_hold_fluid((x, y + 1), fluid) {
+                let below = self[(x, y + 1)].fluid_level as i32;
+                let flow = (stable_bottom_level(remaining + below) - below).clamp(0, remaining.min(MAX_FLOW));
+                if flow > 0 {
+                    transfer(fluid, (x, y), (x, y + 1), flow);
+                    remaining -= flow;
+                }
+            }
+
+            // both sides get their share of the same level so that fluid spreads as fast to the left as to the right
+            let sides = [(x - 1, y), (x + 1, y)].map(|side| match self.can_hold_fluid(side, fluid) {
+                true => (side, lateral_flow(remaining, self[side].fluid_level as i32).min(remaining / 2)),
+                false => (side, 0),
+            });
+            for (side, flow) in sides {
+                if flow > 0 {
+                    transfer(fluid, (x, y), side, flow);
+                    remaining -= flow;
+                }
+            }
+
+            if remaining > 0 && self.can_hold_fluid((x, y - 1), fluid) {
+                let above = self[(x, y - 1)].fluid_level as i32;
+                let flow = (remaining - stable_bottom_level(remaining + above)).clamp(0, remaining.min(MAX_FLOW));
+                if flow > 0 {
+                    transfer(fluid, (x, y), (x, y - 1), flow);
+                }
+            }
+        }
+
+        // the blocks which changed and their neighbours may move during the next tick
+        let mut glow_changes = Vec::new();
+        for (position, [water, lava]) in changes {
+            if water == 0 && lava == 0 {
+                continue;
+            }
+            let block = &mut self[position];
+            if block.fluid_level == 0 && water > 0 && lava > 0 {
+                // water and lava flowing into the same empty block
+                cooling.push(position);
+                continue;
+            }
+            let glow = block.get_light_emission();
+            if block.fluid_level == 0 {
+                block.fluid = if lava > 0 { Fluid::Lava } else { Fluid::Water };
+            }
+            block.fluid_level = (block.fluid_level as i32 + water + lava).clamp(0, u16::MAX as i32) as u16;
+            if block.fluid_level == 0 {
+                block.fluid = Fluid::Water;
+            }
+            if block.get_light_emission() != glow {
+                glow_changes.push(position);
+            }
+            active.insert(position);
+            active.extend(neighbours(position));
+        }
+
+        for position in cooling {
+            let block = self.index_mut_and_render(position);
+            block.block_type = BlockType::from_name("stone");
+            block.fluid = Fluid::Water;
+            block.fluid_level = 0;
+            glow_changes.push(position);
+            active.extend(neighbours(position));
+        }
+
+        self.fluid_update = active;
+        if !glow_changes.is_empty() {
+            for position in glow_changes {
+                self.queue_light_update(position);
+            }
+            self.spread_lights();
+        }
+    }
+}
@@ -1,20 +1,96 @@
-use crate::blocks::BlockType;
-
-#[derive(Copy, Clone, PartialEq)]
-pub enum Item {
-    Dirt,
-    Log,
-    WoodStick,
-    Foliage,
+use crate::blocks::{BlockType, BLOCKS};
+use serde::Deserialize;
+use std::{convert::TryFrom, sync::LazyLock};
+
+/// The definitions of every item, loaded from `ressources/items.json`.
+pub static ITEMS: LazyLock<ItemRegistry> = LazyLock::new(|| {
+    ItemRegistry::from_json(include_str!("../pkg/ressources/items.json")).unwrap()
+});
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemProperties {
+    pub name: String,
+    /// Path of the image shown in the inventory, from the directory containing the `ressources` folder.
+    pub icon: String,
+}
+
+pub struct ItemRegistry {
+    items: Vec<ItemProperties>,
+}
+
+impl ItemRegistry {
+    pub fn from_json(json: &str) -> Result<ItemRegistry, String> {
+        let items: Vec<ItemProperties> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if items.len() > u16::MAX as usize {
+            return Err(format!("too many items ({})", items.len()));
+        }
+        for (idx, item) in items.iter().enumerate() {
+            if items[..idx].iter().any(|previous| previous.name == item.name) {
+                return Err(format!("{} is defined twice", item.name));
+            }
+        }
+        Ok(ItemRegistry { items })
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<Item> {
+        self.items.iter().position(|item| item.name == name).map(|idx| Item(idx as u16))
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Item> {
+        (0..self.items.len() as u16).map(Item)
+    }
 }
 
+impl std::ops::Index<Item> for ItemRegistry {
+    type Output = ItemProperties;
+
+    fn index(&self, item: Item) -> &ItemProperties {
+        &self.items[item.0 as usize]
+    }
+}
+
+/// The id of an item in the registry. Items are written with their name in json files.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Item(pub u16);
+
 impl Item {
-    pub fn as_block(&self) -> Option<BlockType> {
-        match self {
-            Item::Dirt => Some(BlockType::Dirt),
-            _ => None,
+    /// Panics if no item has this name.
+    pub fn from_name(name: &str) -> Item {
+        match ITEMS.get_by_name(name) {
+            Some(item) => item,
+            None => panic!("there is no item named {}", name),
         }
     }
+
+    pub fn get_properties(self) -> &'static ItemProperties {
+        &ITEMS[self]
+    }
+
+    pub fn get_name(self) -> &'static str {
+        &self.get_properties().name
+    }
+
+    /// Items can be placed as the block with the same name, if there is one.
+    pub fn as_block(self) -> Option<BlockType> {
+        BLOCKS.get_by_name(self.get_name())
+    }
+}
+
+impl TryFrom<String> for Item {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Item, String> {
+        ITEMS.get_by_name(&name).ok_or_else(|| format!("there is no item named {}", name))
+    }
 }
 
 pub struct Inventory {
@@ -30,13 +106,19 @@ impl Inventory {
         Inventory { slots }
     }
 
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
     pub fn push(&mut self, item: Item) -> bool {
-        for slot in &mut self.slots {
-            if let Some((slot_item, quantity)) = slot {
-                if slot_item == &item {
-                    *quantity += 1;
-                    return true;
-                }
+        for (slot_item, quantity) in self.slots.iter_mut().flatten() {
+            if slot_item == &item {
+                *quantity += 1;
+                return true;
             }
         }
 
@@ -1,9 +1,14 @@
+#[cfg(target_arch = "wasm32")]
 use console_error_panic_hook::set_once;
+#[cfg(target_arch = "wasm32")]
 use std::{cell::RefCell, rc::Rc};
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::{prelude::*, JsCast};
+#[cfg(target_arch = "wasm32")]
 use wasm_game_lib::inputs::event::types::*;
+#[cfg(target_arch = "wasm32")]
 use wasm_game_lib::{
-    graphics::{color::Color, window::Window},
+    graphics::window::Window,
     inputs::{
         event::Event,
         keyboard::{Key, KeyboardEvent},
@@ -11,20 +16,45 @@ use wasm_game_lib::{
     },
 };
 
+pub mod biomes;
 pub mod blocks;
+pub mod caves;
+pub mod clock;
 pub mod coords;
+pub mod export;
+pub mod falling;
+pub mod fluid;
 pub mod items;
+pub mod light;
 pub mod loader;
 pub mod map;
+pub mod noise;
 pub mod player;
 pub mod progress_bar;
+pub mod renderer;
+pub mod save;
+pub mod seed;
+pub mod software_renderer;
+pub mod structures;
 pub mod textures;
+pub mod trees;
 pub mod chunks;
-use blocks::BlockType;
-use map::Map;
-use player::Player;
-use textures::Textures;
+pub mod water;
+pub mod world;
+#[cfg(target_arch = "wasm32")]
+use {
+    blocks::BlockType,
+    map::Map,
+    player::Player,
+    renderer::CanvasRenderer,
+    save::{LocalStorage, Storage},
+    seed::WorldSeed,
+    textures::Textures,
+    trees::TreePart,
+    world::World,
+};
 
+#[cfg(target_arch = "wasm32")]
 fn request_animation_frame(f: &Closure<dyn FnMut()>) {
     web_sys::window()
         .unwrap()
@@ -32,6 +62,31 @@ fn request_animation_frame(f: &Closure<dyn FnMut()>) {
         .expect("should register `requestAnimationFrame` OK");
 }
 
+/// Saves the world when the page is closed or hidden, since the last periodic save can be several seconds old.
+#[cfg(target_arch = "wasm32")]
+fn save_when_leaving(world: &Rc<RefCell<World>>, player: &Rc<RefCell<Player>>) {
+    let (world, player) = (Rc::clone(world), Rc::clone(player));
+    let save = Closure::wrap(Box::new(move || world.borrow_mut().save(&player.borrow())) as Box<dyn FnMut()>);
+    let window = web_sys::window().unwrap();
+    window
+        .add_event_listener_with_callback("beforeunload", save.as_ref().unchecked_ref())
+        .unwrap();
+    window
+        .document()
+        .unwrap()
+        .add_event_listener_with_callback("visibilitychange", save.as_ref().unchecked_ref())
+        .unwrap();
+    save.forget();
+}
+
+/// Reads the seed given in the url of the page, like `?seed=1234`.
+#[cfg(target_arch = "wasm32")]
+fn get_url_seed() -> Option<WorldSeed> {
+    let search = web_sys::window()?.location().search().ok()?;
+    let seed = web_sys::UrlSearchParams::new_with_str(&search).ok()?.get("seed")?;
+    Some(WorldSeed::parse(&seed))
+}
+
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(start)]
 pub async fn start() {
@@ -47,11 +102,34 @@ pub async fn start() {
     );
 
     let textures = Rc::new(Textures::load(&mut canvas).await);
+    let mut canvas = CanvasRenderer::new(canvas, textures);
+
+    // each seed given in the url gets its own save
+    let url_seed = get_url_seed();
+    let save_name = match url_seed {
+        Some(seed) => format!("terrarust-{}", seed),
+        None => String::from("terrarust"),
+    };
+    let (mut world, player) = match LocalStorage::new(&save_name).map(|storage| World::load(Box::new(storage))) {
+        Some(Ok(save)) => save,
+        result => {
+            if let Some(Err(e)) = result {
+                wasm_game_lib::log!("starting a new world: {:?}", e);
+            }
+            let mut world = World::new(url_seed.unwrap_or_else(WorldSeed::random));
+            world.storage = LocalStorage::new(&save_name).map(|storage| Box::new(storage) as Box<dyn Storage>);
+            (world, Player::new())
+        }
+    };
+    let mut map = Map::new(&canvas, &mut world);
+
+    // the world is shared with the listeners saving it when the page is left
+    let world = Rc::new(RefCell::new(world));
+    let player = Rc::new(RefCell::new(player));
+    save_when_leaving(&world, &player);
 
-    let mut player = Player::new(Rc::clone(&textures));
-    let mut map = Map::new(Rc::clone(&textures));
-    
     let mut frame = 0;
+    let mut last_frame = js_sys::Date::now();
 
     let mut direction_keys = (false, false, false, false);
 
@@ -60,6 +138,12 @@ pub async fn start() {
 
     let window2 = Rc::clone(&window);
     *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+        let mut world = world.borrow_mut();
+        let mut player = player.borrow_mut();
+        let now = js_sys::Date::now();
+        let elapsed = (now - last_frame) / 1000.0;
+        last_frame = now;
+
         for event in window.borrow_mut().poll_events() {
             #[allow(clippy::single_match)]
             match event {
@@ -70,6 +154,7 @@ pub async fn start() {
                         Key::DownArrow => direction_keys.2 = true,
                         Key::LeftArrow => direction_keys.3 = true,
                         Key::E => player.change_inventory_state(),
+                        Key::L => map.set_smooth_lighting(&world, !map.is_lighting_smooth()),
                         _ => (),
                     },
                     KeyboardEvent::Up(key) => match key {
@@ -80,16 +165,13 @@ pub async fn start() {
                         _ => (),
                     },
                 },
-                Event::MouseEvent(event) => match event {
-                    MouseEvent::Scroll(_, movement, _, _) => {
-                        if movement > 0.0 && player.selected_slot < 8 {
-                            player.selected_slot += 1;
-                        } else if movement < 0.0 && player.selected_slot > 0 {
-                            player.selected_slot -= 1;
-                        }
+                Event::MouseEvent(MouseEvent::Scroll(_, movement, _, _)) => {
+                    if movement > 0.0 && player.selected_slot < 8 {
+                        player.selected_slot += 1;
+                    } else if movement < 0.0 && player.selected_slot > 0 {
+                        player.selected_slot -= 1;
                     }
-                    _ => (),
-                },
+                }
                 _ => (),
             }
         }
@@ -101,15 +183,32 @@ pub async fn start() {
                 &player,
                 screen_center,
             );
-            if map[(x, y)].block_type != BlockType::Air {
-                let items = map[(x, y)].as_item();
-                for item in items {
-                    player.inventory.push(item);
+            if player.mine((x, y), &world, elapsed) {
+                let broken = world[(x, y)].block_type;
+                if broken != BlockType::AIR {
+                    let items = world[(x, y)].as_item();
+                    for item in items {
+                        player.inventory.push(item);
+                    }
+                }
+                world.index_mut_and_render((x, y)).block_type = BlockType::AIR;
+                // felling a log topples the part of the tree above it
+                if broken == TreePart::Log.get_block_type() {
+                    for item in world.fell_tree((x, y)) {
+                        player.inventory.push(item);
+                    }
                 }
+                if broken == BlockType::from_name("chest") {
+                    for item in world.open_chest((x, y)) {
+                        player.inventory.push(item);
+                    }
+                }
+                world.update_light((x, y));
+                world.wake_fluid((x, y));
+                world.check_support((x, y - 1));
             }
-            map.index_mut_and_render((x, y)).block_type = BlockType::Air;
-            map.light_update.push((x, y, false));
-            map.spread_lights();
+        } else {
+            player.stop_mining();
         }
 
         if is_pressed(Button::Secondary) {
@@ -119,35 +218,41 @@ pub async fn start() {
                 &player,
                 screen_center,
             );
-            if map[(x, y)].block_type == BlockType::Air {
-                if let Some((item, quantity)) = &mut player.inventory[player.selected_slot as usize]
+            let slot = player.selected_slot as usize;
+            if world[(x, y)].block_type == BlockType::AIR {
+                if let Some((item, quantity)) = &mut player.inventory[slot]
                 {
                     if *quantity > 0 {
                         if let Some(block) = item.as_block() {
                             *quantity -= 1;
                             if *quantity == 0 {
-                                player.inventory[player.selected_slot as usize] = None;
+                                player.inventory[slot] = None;
                             }
-                            map.index_mut_and_render((x, y)).block_type = block;
-                            map.light_update.push((x, y, true));
-                            map.spread_lights();
-                            map.light_update.push((x, y, false));
-                            map.spread_lights();
+                            world.index_mut_and_render((x, y)).block_type = block;
+                            world.update_light((x, y));
+                            world.wake_fluid((x, y));
+                            world.check_support((x, y));
                         }
                     }
                 }
             }
         }
 
-        player.handle_events(direction_keys, &map, frame);
-        map.update_chunks(&player);
-        map.flow_water();
+        player.handle_events(direction_keys, &world, frame);
+        player.breathe(&world, elapsed);
+        world.update_chunks(&player);
+        world.update_fluids(elapsed);
+        world.update_falling_blocks();
+        world.clock.update(elapsed);
+        map.update(&mut world, &player);
 
-        canvas.clear_with_color(Color::cyan());
-        map.draw_on_canvas(&mut canvas, &player, screen_center);
+        map.draw_on_canvas(&mut canvas, &world, &player, screen_center);
         player.draw_on_canvas(&mut canvas, screen_center, frame);
 
         frame += 1;
+        if frame % 600 == 0 {
+            world.save(&player);
+        }
 
         request_animation_frame(f.borrow().as_ref().unwrap());
     }) as Box<dyn FnMut()>));
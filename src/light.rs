@@ -0,0 +1,325 @@
+//! Light propagation.
+//!
+//! Light comes from sources, the top row of the sky and the blocks with a `light_emission`, and loses the `light_loss` of each block it enters.
+//! The red, green and blue channels spread independently so that colored sources tint their surroundings.
+//! Sunlight has its own channel: at full strength it falls straight down through the blocks which are not solid without losing anything,
+//! and it diffuses like any other light once it hits a solid block.
+//! Changes are spread with two breadth-first passes: the removal pass darkens the blocks which were lit through a changed block
+//! and collects the brighter blocks around them, then the addition pass spreads light again from these blocks and from the sources.
+
+use crate::{
+    chunks::{CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_COUNT, SECTION_SIZE},
+    world::World,
+};
+use serde::Deserialize;
+
+/// The brightest light, the one of the sky.
+pub const MAX_LIGHT: u8 = 100;
+const SKY_LIGHT: Light = Light::sunlight(MAX_LIGHT);
+/// The index of the sunlight in `Light::channels`.
+const SUNLIGHT_CHANNEL: usize = 3;
+
+/// Red, green and blue block light and sunlight levels between 0 and `MAX_LIGHT`.
+/// Written `[red, green, blue]` in block definitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(from = "[u8; 3]")]
+pub struct Light {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub sun: u8,
+}
+
+impl Light {
+    pub const BLACK: Light = Light::new(0, 0, 0);
+
+    pub const fn new(red: u8, green: u8, blue: u8) -> Light {
+        Light { red, green, blue, sun: 0 }
+    }
+
+    pub const fn sunlight(level: u8) -> Light {
+        Light { red: 0, green: 0, blue: 0, sun: level }
+    }
+
+    /// Red, green, blue and sunlight levels.
+    pub fn channels(self) -> [u8; 4] {
+        [self.red, self.green, self.blue, self.sun]
+    }
+
+    pub fn from_channels([red, green, blue, sun]: [u8; 4]) -> Light {
+        Light { red, green, blue, sun }
+    }
+
+    /// The red, green and blue levels seen in the block, sunlight being white and scaled by the `daylight` level.
+    pub fn color(self, daylight: u8) -> [u8; 3] {
+        let sun = (self.sun as usize * daylight as usize / MAX_LIGHT as usize) as u8;
+        [self.red.max(sun), self.green.max(sun), self.blue.max(sun)]
+    }
+
+    pub fn is_black(self) -> bool {
+        self == Light::BLACK
+    }
+
+    /// The level of the brightest channel.
+    pub fn brightness(self) -> u8 {
+        self.red.max(self.green).max(self.blue).max(self.sun)
+    }
+
+    /// The brightest level of each channel.
+    pub fn max(self, other: Light) -> Light {
+        let [a, b] = [self.channels(), other.channels()];
+        Light::from_channels([0, 1, 2, 3].map(|channel| a[channel].max(b[channel])))
+    }
+
+    pub fn saturating_sub(self, loss: u8) -> Light {
+        Light::from_channels(self.channels().map(|level| level.saturating_sub(loss)))
+    }
+}
+
+impl From<[u8; 3]> for Light {
+    fn from([red, green, blue]: [u8; 3]) -> Light {
+        Light::new(red, green, blue)
+    }
+}
+
+/// A set of blocks of the loaded chunks, one bit per block.
+#[derive(Debug, Default)]
+pub struct BlockSet {
+    first_x: isize,
+    width: usize,
+    bits: Vec<u64>,
+}
+
+impl BlockSet {
+    /// Makes the set cover the loaded chunks. The bits are only allocated again when chunks were loaded or unloaded
+    /// since every block inserted during a light update is removed before it ends.
+    fn cover(&mut self, world: &World) {
+        let first_x = world.first_chunk_number * CHUNK_WIDTH as isize;
+        let width = world.chunks.len() * CHUNK_WIDTH;
+        if first_x != self.first_x || width != self.width || self.bits.is_empty() {
+            *self = BlockSet {
+                first_x,
+                width,
+                bits: vec![0; (width * CHUNK_HEIGHT).div_ceil(64)],
+            };
+        }
+    }
+
+    fn index(&self, (x, y): (isize, isize)) -> usize {
+        (y as usize) * self.width + (x - self.first_x) as usize
+    }
+
+    /// Returns whether the block was not in the set yet.
+    fn insert(&mut self, position: (isize, isize)) -> bool {
+        let idx = self.index(position);
+        let inserted = self.bits[idx / 64] & (1 << (idx % 64)) == 0;
+        self.bits[idx / 64] |= 1 << (idx % 64);
+        inserted
+    }
+
+    fn remove(&mut self, position: (isize, isize)) {
+        let idx = self.index(position);
+        self.bits[idx / 64] &= !(1 << (idx % 64));
+    }
+}
+
+/// Interpolates the colors of the top left, top right, bottom left and bottom right corners of a block
+/// at a position of the block between `(0.0, 0.0)` and `(1.0, 1.0)`.
+pub fn interpolate_corners(corners: [[u8; 3]; 4], (u, v): (f64, f64)) -> [u8; 3] {
+    let [top_left, top_right, bottom_left, bottom_right] = corners;
+    [0, 1, 2].map(|channel| {
+        let top = top_left[channel] as f64 * (1.0 - u) + top_right[channel] as f64 * u;
+        let bottom = bottom_left[channel] as f64 * (1.0 - u) + bottom_right[channel] as f64 * u;
+        (top * (1.0 - v) + bottom * v).round() as u8
+    })
+}
+
+fn neighbours((x, y): (isize, isize)) -> [(isize, isize); 4] {
+    [(x + 1, y), (x - 1, y), (x, y - 1), (x, y + 1)]
+}
+
+/// Whether a channel keeps its full level when going from a block to its neighbour.
+fn falls_through(channel: usize, level: u8, (x, y): (isize, isize), neighbour: (isize, isize), world: &World) -> bool {
+    channel == SUNLIGHT_CHANNEL && level == MAX_LIGHT && neighbour == (x, y + 1) && world[neighbour].can_pass_through()
+}
+
+impl World {
+    /// Light emitted by a block whatever its neighbours.
+    pub fn get_emitted_light(&self, (x, y): (isize, isize)) -> Light {
+        let sky_light = match y {
+            0 => SKY_LIGHT,
+            _ => Light::BLACK,
+        };
+        sky_light.max(self[(x, y)].get_light_emission())
+    }
+
+    /// The light seen in a block at the current time of the day.
+    pub fn get_light_color(&self, position: (isize, isize)) -> [u8; 3] {
+        self[position].light.color(self.clock.daylight())
+    }
+
+    /// The light seen at the top left corner of a block, the average of the four blocks sharing this corner.
+    pub fn get_corner_light(&self, (x, y): (isize, isize)) -> [u8; 3] {
+        let colors = [(x - 1, y - 1), (x, y - 1), (x - 1, y), (x, y)].map(|position| self.get_light_color(position));
+        [0, 1, 2].map(|channel| ((colors.iter().map(|color| color[channel] as usize).sum::<usize>() + 2) / 4) as u8)
+    }
+
+    /// The light seen at the top left, top right, bottom left and bottom right corners of a block.
+    pub fn get_corner_lights(&self, (x, y): (isize, isize)) -> [[u8; 3]; 4] {
+        [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)].map(|corner| self.get_corner_light(corner))
+    }
+
+    /// Computes the light of all the generated sections from scratch.
+    pub fn init_lights(&mut self) {
+        self.light_additions.clear();
+        self.light_removals.clear();
+
+        for chunk_index in 0..self.chunks.len() {
+            for section in 0..SECTION_COUNT {
+                if !self.chunks[chunk_index].is_generated(section) {
+                    continue;
+                }
+                let (x0, y0) = self.section_origin(chunk_index, section);
+                for x in x0..x0 + CHUNK_WIDTH as isize {
+                    for y in y0..y0 + SECTION_SIZE as isize {
+                        if !self[(x, y)].light.is_black() {
+                            self.set_light((x, y), Light::BLACK);
+                        }
+                    }
+                }
+                self.queue_light_sources(chunk_index, section);
+            }
+        }
+
+        self.spread_lights();
+    }
+
+    fn queue_light_sources(&mut self, chunk_index: usize, section: usize) {
+        let (x0, y0) = self.section_origin(chunk_index, section);
+        for x in x0..x0 + CHUNK_WIDTH as isize {
+            for y in y0..y0 + SECTION_SIZE as isize {
+                let light = self[(x, y)].light;
+                let lit = light.max(self.get_emitted_light((x, y)));
+                if lit != light {
+                    self.set_light((x, y), lit);
+                    self.light_additions.push_back((x, y));
+                }
+            }
+        }
+    }
+
+    /// Lights the sources of a section which was just generated and lets the light of its neighbours in.
+    pub fn queue_section_lights(&mut self, chunk_index: usize, section: usize) {
+        self.queue_light_sources(chunk_index, section);
+
+        let (x0, y0) = self.section_origin(chunk_index, section);
+        let (width, height) = (CHUNK_WIDTH as isize, SECTION_SIZE as isize);
+        for x in x0..x0 + width {
+            self.light_additions.push_back((x, y0 - 1));
+            self.light_additions.push_back((x, y0 + height));
+        }
+        for y in y0..y0 + height {
+            self.light_additions.push_back((x0 - 1, y));
+            self.light_additions.push_back((x0 + width, y));
+        }
+    }
+
+    /// Removes the light of a block which changed, so that the next `spread_lights` lights it again.
+    pub fn queue_light_update(&mut self, position: (isize, isize)) {
+        if self.is_generated(position) {
+            let light = self[position].light;
+            self.set_light(position, Light::BLACK);
+            self.light_removals.push_back((position.0, position.1, light));
+        }
+    }
+
+    /// Spreads light again around a block which changed.
+    pub fn update_light(&mut self, position: (isize, isize)) {
+        self.queue_light_update(position);
+        self.spread_lights();
+    }
+
+    /// Runs the removal pass then the addition pass until every queued change is spread.
+    pub fn spread_lights(&mut self) {
+        if self.light_removals.is_empty() && self.light_additions.is_empty() {
+            return;
+        }
+        let mut queued = std::mem::take(&mut self.light_queued);
+        queued.cover(self);
+        for position in self.light_additions.drain(..).collect::<Vec<_>>() {
+            if self.is_generated(position) && queued.insert(position) {
+                self.light_additions.push_back(position);
+            }
+        }
+
+        while let Some((x, y, light)) = self.light_removals.pop_front() {
+            for neighbour in neighbours((x, y)) {
+                if !self.is_generated(neighbour) {
+                    continue;
+                }
+                // in each channel, a darker neighbour may have been lit through this block and a brighter one has to light it again,
+                // except for the sunlight falling from this block which is as bright below it
+                let mut kept = self[neighbour].light.channels();
+                let mut removed = [0; 4];
+                let mut relights = false;
+                for (channel, level) in light.channels().iter().enumerate() {
+                    if kept[channel] == 0 || *level == 0 {
+                        continue;
+                    }
+                    if kept[channel] < *level || falls_through(channel, *level, (x, y), neighbour, self) {
+                        removed[channel] = kept[channel];
+                        kept[channel] = 0;
+                    } else {
+                        relights = true;
+                    }
+                }
+
+                if removed != [0; 4] {
+                    self.set_light(neighbour, Light::from_channels(kept));
+                    self.light_removals.push_back((neighbour.0, neighbour.1, Light::from_channels(removed)));
+                }
+                if relights && queued.insert(neighbour) {
+                    self.light_additions.push_back(neighbour);
+                }
+            }
+
+            let light = self[(x, y)].light;
+            let lit = light.max(self.get_emitted_light((x, y)));
+            if lit != light {
+                self.set_light((x, y), lit);
+                if queued.insert((x, y)) {
+                    self.light_additions.push_back((x, y));
+                }
+            }
+        }
+
+        while let Some(position) = self.light_additions.pop_front() {
+            queued.remove(position);
+            let light = self[position].light;
+            for neighbour in neighbours(position) {
+                if !self.is_generated(neighbour) {
+                    continue;
+                }
+                let neighbour_light = self[neighbour].light;
+                let loss = self[neighbour].block_type.get_light_loss();
+                let mut spread = light.saturating_sub(loss);
+                if falls_through(SUNLIGHT_CHANNEL, light.sun, position, neighbour, self) {
+                    spread.sun = MAX_LIGHT;
+                }
+                let lit = neighbour_light.max(spread);
+                if lit != neighbour_light {
+                    self.set_light(neighbour, lit);
+                    if queued.insert(neighbour) {
+                        self.light_additions.push_back(neighbour);
+                    }
+                }
+            }
+        }
+        self.light_queued = queued;
+    }
+
+    fn set_light(&mut self, position: (isize, isize), light: Light) {
+        self[position].light = light;
+        self.light_to_render.push_back(position);
+    }
+}
@@ -12,7 +12,7 @@ use wasm_game_lib::{
     system::sleep,
 };
 
-pub async fn load_images(images: Vec<&str>, mut canvas: &mut Canvas) -> Vec<Image> {
+pub async fn load_images(images: Vec<&str>, canvas: &mut Canvas) -> Vec<Image> {
     let mut receivers = Vec::new();
     let mut senders = Vec::new();
     for _i in 0..images.len() {
@@ -26,7 +26,7 @@ pub async fn load_images(images: Vec<&str>, mut canvas: &mut Canvas) -> Vec<Imag
         futures.push(Image::load_and_send(image, senders.remove(0)))
     }
 
-    let results = join!(loading_tracker(receivers, &mut canvas), join_all(futures)).0;
+    let results = join!(loading_tracker(receivers, canvas), join_all(futures)).0;
     let mut images = Vec::new();
     for result in results {
         images.push(result.expect("failed to load an image"));
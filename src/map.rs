@@ -1,709 +1,473 @@
 use crate::{
-    blocks::{Block, BlockType, NaturalBackground},
-    coords::{map_to_screen, x_to_chunk, x_to_chunk_and_column},
+    blocks::{Block, BlockTexture, NaturalBackground},
+    chunks::{SECTION_COUNT, SECTION_SIZE},
+    clock::Clock,
+    coords::{map_to_screen, x_to_chunk_and_column},
     player::Player,
-    textures::{get_texture_idx, Textures},
-    chunks::Chunk,
+    renderer::{CompositeMode, Renderer},
+    textures::{get_texture_idx, Sprite},
+    fluid::{Fluid, FLUID_FULL},
+    light::{interpolate_corners, MAX_LIGHT},
+    world::{World, SECTION_DISTANCE},
 };
-use std::rc::Rc;
 #[allow(unused_imports)]
 use wasm_game_lib::{graphics::{canvas::*, color::*}, log, elog};
 
-#[cfg(target_arch = "wasm32")]
-pub struct Map {
-    chunks: Vec<(Chunk, Canvas, Canvas)>,
-    light_to_render: Vec<(isize, isize)>,
-    canvas: Canvas,
-    pub first_chunk_number: isize,
-    pub first_block: usize,
-    textures: Rc<Textures>,
-    pub air: Block,
-    pub light_update: Vec<(isize, isize, bool)>,
-    pub water_update: Vec<(isize, isize)>,
+const fn opaque(red: u8, green: u8, blue: u8) -> Color {
+    Color { red, green, blue, alpha: 255 }
+}
+
+/// Colors of the top of the sky and of the horizon.
+const DAY_SKY: (Color, Color) = (opaque(80, 160, 230), opaque(135, 206, 235));
+const NIGHT_SKY: (Color, Color) = (opaque(40, 50, 110), opaque(70, 90, 160));
+/// Horizon color when the sun is rising or setting.
+const TWILIGHT: Color = opaque(250, 150, 80);
+
+const WATER_LINE_STYLE: LineStyle = LineStyle {
+    cap: LineCap::Square,
+    color: Color {
+        red: 0,
+        green: 0,
+        blue: 255,
+        alpha: 255,
+    },
+    size: 3.0,
+    join: LineJoin::Bevel,
+};
+
+const LAVA_LINE_STYLE: LineStyle = LineStyle {
+    cap: LineCap::Square,
+    color: Color {
+        red: 255,
+        green: 100,
+        blue: 0,
+        alpha: 255,
+    },
+    size: 3.0,
+    join: LineJoin::Bevel,
+};
+
+/// Squares drawn on each side of a block when lighting is smooth.
+const SMOOTH_LIGHT_STEPS: usize = 4;
+
+/// Fluid thinner than a pixel is not drawn.
+const VISIBLE_FLUID: u16 = FLUID_FULL / 16;
+
+/// Height of the fluid drawn in a block, in pixels.
+fn fluid_height(level: u16) -> f64 {
+    level.min(FLUID_FULL) as f64 * 16.0 / FLUID_FULL as f64
+}
+
+fn light_to_color(light: [u8; 3]) -> Color {
+    let [red, green, blue] = light.map(|level| (level.min(MAX_LIGHT) as usize * 255 / MAX_LIGHT as usize) as u8);
+    Color::new(red, green, blue)
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub struct Map {
-    pub chunks: Vec<(Chunk, (), (), Vec<(usize, usize)>, Vec<(usize, usize)>)>,
+/// The rendering layer of the game.
+/// It owns a block layer and a light layer per rendered section and keeps them in sync with a `World`.
+/// Only the generated sections close to the player are rendered.
+/// Blocks are drawn on `canvas` and multiplied by the colors of `light`, where light layers are merged.
+/// The sky is drawn behind the blocks which let it show.
+pub struct Map<R: Renderer> {
+    chunks: Vec<Vec<Option<(R, R)>>>,
+    canvas: R,
+    light: R,
+    /// The daylight level the light layers are rendered with.
+    daylight: u8,
+    /// Whether light is interpolated between the corners of blocks instead of being flat on each block.
+    smooth_lighting: bool,
     pub first_chunk_number: isize,
-    pub first_block: usize,
-    pub air: Block,
-    pub to_update_chunks: Vec<usize>,
-    pub light_update: Vec<(isize, isize, bool)>,
 }
 
-impl Map {
-    #[cfg(target_arch = "wasm32")]
-    pub fn new(textures: Rc<Textures>) -> Map {
+impl<R: Renderer> Map<R> {
+    pub fn new(renderer: &R, world: &mut World) -> Map<R> {
+        let (width, height) = renderer.get_size();
         let mut map = Map {
             chunks: Vec::new(),
-            light_to_render: Vec::with_capacity(2048),
-            textures,
-            first_chunk_number: -5,
-            first_block: 0,
-            air: Block {
-                block_type: BlockType::Air,
-                natural_background: NaturalBackground::Sky,
-                light: 0,
-                water: 0.0,
-            },
-            canvas: Canvas::new(),
-            light_update: Vec::with_capacity(2048),
-            water_update: Vec::with_capacity(2048),
+            first_chunk_number: world.first_chunk_number,
+            canvas: renderer.create_layer(width, height),
+            light: renderer.create_layer(width, height),
+            daylight: world.clock.daylight(),
+            smooth_lighting: false,
         };
-        map.canvas.set_width(32 * 16 * 9);
-        map.canvas.set_height(2048 * 16);
-        let mut height: f64 = 20.0;
-        let mut slope: f64 = 0.2;
-        for i in -5..5 {
-            let mut chunk_canvas = Canvas::new();
-            chunk_canvas.set_width(42 * 16);
-            chunk_canvas.set_height(2048 * 16);
-
-            let mut light_chunk_canvas = Canvas::new();
-            light_chunk_canvas.set_width(42 * 16);
-            light_chunk_canvas.set_height(2048 * 16);
-
-            map.chunks.push((
-                Chunk::generate(&mut height, &mut slope, true, i * 32),
-                chunk_canvas,
-                light_chunk_canvas
-            ));
+        for _ in 0..world.chunks.len() {
+            map.chunks.push(Map::new_chunk());
         }
-
-        for chunk_index in 0..10 {
-            map.chunks[chunk_index]
-                .1
-                .context
-                .set_fill_style(&wasm_bindgen::JsValue::from_str("rgb(135,206,235)"));
-            map.init_water(chunk_index);
-        }
-        
-        map.init_lights();
-        for x in -160..160 {
-            for y in 0..100 {
-                map.render_block(x, y);
-                map.render_light(x, y);
-            }
-        }
-        map.light_to_render.clear();
+        // sections are entirely rendered when they get close to the player
+        world.blocks_to_render.clear();
+        world.light_to_render.clear();
 
         map
     }
 
-    fn render_block(&mut self, x: isize, y: isize) {
-        let (chunk, x_idx) = x_to_chunk_and_column(x);
-        let chunk_index = (chunk - self.first_chunk_number) as usize;
-        if chunk_index >= self.chunks.len() {
-            return;
+    fn new_chunk() -> Vec<Option<(R, R)>> {
+        (0..SECTION_COUNT).map(|_| None).collect()
+    }
+
+    fn new_section_layers(&self) -> (R, R) {
+        let size = (SECTION_SIZE * 16) as u32;
+        (self.canvas.create_layer(size, size), self.canvas.create_layer(size, size))
+    }
+
+    /// Returns the layers of the section containing a block, if it is rendered.
+    fn section_layers(&mut self, x: isize, y: isize) -> Option<&mut (R, R)> {
+        let chunk_index = x_to_chunk_and_column(x).0 - self.first_chunk_number;
+        if chunk_index < 0 || y < 0 {
+            return None;
         }
-        let block = &self[(x, y)];
+        self.chunks.get_mut(chunk_index as usize)?.get_mut(y as usize / SECTION_SIZE)?.as_mut()
+    }
+
+    /// Position of a block in its section, in blocks.
+    fn position_in_section(x: isize, y: isize) -> (f64, f64) {
+        (x_to_chunk_and_column(x).1 as f64, y.rem_euclid(SECTION_SIZE as isize) as f64)
+    }
+
+    fn render_block(&mut self, world: &World, x: isize, y: isize) {
+        let block = &world[(x, y)];
 
+        // blocks which can be passed through, like leaves, have no border between each other
+        let has_border = |neighbour: &Block| neighbour.can_pass_through() && neighbour.block_type != block.block_type;
         let block_texture_idx = get_texture_idx((
-            self[(x, y - 1)].can_pass_through(),
-            self[(x + 1, y)].can_pass_through(),
-            self[(x, y + 1)].can_pass_through(),
-            self[(x - 1, y)].can_pass_through(),
+            has_border(&world[(x, y - 1)]),
+            has_border(&world[(x + 1, y)]),
+            has_border(&world[(x, y + 1)]),
+            has_border(&world[(x - 1, y)]),
+        ));
+        let background_texture_idx = get_texture_idx((
+            world[(x, y - 1)].natural_background == NaturalBackground::Sky,
+            world[(x + 1, y)].natural_background == NaturalBackground::Sky,
+            world[(x, y + 1)].natural_background == NaturalBackground::Sky,
+            world[(x - 1, y)].natural_background == NaturalBackground::Sky,
         ));
 
-        self.chunks[chunk_index].1.context.fill_rect(
-            (x_idx + 5) as f64 * 16.0,
-            y as f64 * 16.0,
-            16.0,
-            16.0,
-        );
+        let (column, row) = Map::<R>::position_in_section(x, y);
+        let (layer, _) = match self.section_layers(x, y) {
+            Some(layers) => layers,
+            None => return,
+        };
+        let position = (column * 16.0, row * 16.0);
+
+        layer.clear_rect(position, (16.0, 16.0));
 
+        // only atlas blocks surrounded by solid blocks hide the whole background
+        let texture = &block.block_type.get_properties().texture;
         if block.natural_background == NaturalBackground::Dirt
-            && (block.block_type == BlockType::Air || block_texture_idx != 0)
+            && (!matches!(texture, Some(BlockTexture::Atlas { .. })) || block_texture_idx != 0)
         {
-            let texture_idx = get_texture_idx((
-                self[(x, y - 1)].natural_background == NaturalBackground::Sky,
-                self[(x + 1, y)].natural_background == NaturalBackground::Sky,
-                self[(x, y + 1)].natural_background == NaturalBackground::Sky,
-                self[(x - 1, y)].natural_background == NaturalBackground::Sky,
-            ));
-            self.chunks[chunk_index].1.context.draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(&self.textures.background_dirt.get_html_element(), texture_idx as f64 * 16.0, 0.0, 16.0, 16.0, (x_idx + 5) as f64 * 16.0, y as f64 * 16.0, 16.0, 16.0).unwrap();
+            layer.draw_sprite_part(Sprite::BackgroundDirt, (background_texture_idx as f64 * 16.0, 0.0), (16.0, 16.0), position);
         }
 
-        match block.block_type {
-            BlockType::Air => (),
-            BlockType::Grass => {
-                self.chunks[chunk_index].1.context.draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(&self.textures.grass.get_html_element(), block_texture_idx as f64 * 16.0, 0.0, 16.0, 16.0, (x_idx + 5) as f64 * 16.0, y as f64 * 16.0, 16.0, 16.0).unwrap();
-            }
-            BlockType::Dirt => {
-                self.chunks[chunk_index].1.context.draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(&self.textures.dirt.get_html_element(), block_texture_idx as f64 * 16.0, 0.0, 16.0, 16.0, (x_idx + 5) as f64 * 16.0, y as f64 * 16.0, 16.0, 16.0).unwrap();
-            }
-            BlockType::Tree => self.chunks[chunk_index].1.draw_image(
-                (
-                    (x_idx + 5) as f64 * 16.0 - 80.0,
-                    y as f64 * 16.0 - 240.0,
-                ),
-                &self.textures.tree,
+        match texture {
+            None => (),
+            Some(BlockTexture::Atlas { atlas }) => layer.draw_sprite_part(
+                Sprite::Blocks,
+                ((atlas.0 as usize + block_texture_idx) as f64 * 16.0, atlas.1 as f64 * 16.0),
+                (16.0, 16.0),
+                position,
             ),
+            Some(BlockTexture::Sprite { sprite, offset }) => {
+                layer.draw_sprite(*sprite, (position.0 + offset.0, position.1 + offset.1))
+            }
         }
     }
 
-    fn render_light(&mut self, x: isize, y: isize) {
-        use wasm_bindgen::JsValue;
+    fn render_light(&mut self, world: &World, x: isize, y: isize) {
+        let smooth_lighting = self.smooth_lighting;
+        let (column, row) = Map::<R>::position_in_section(x, y);
+        let (_, layer) = match self.section_layers(x, y) {
+            Some(layers) => layers,
+            None => return,
+        };
 
-        let (chunk, x) = x_to_chunk_and_column(x);
-        let (x, y) = (x as usize, y as usize);
-        let chunk_index = (chunk - self.first_chunk_number) as usize;
-        if chunk_index >= self.chunks.len() {
+        if !smooth_lighting {
+            layer.fill_rect((column * 16.0, row * 16.0), (16.0, 16.0), &light_to_color(world.get_light_color((x, y))));
             return;
         }
-
-        self.chunks[chunk_index]
-            .2
-            .context
-            .set_fill_style(&JsValue::from(format!(
-                "rgba(255,255,255,0.{:02})",
-                self.chunks[chunk_index].0.blocks[x][y].light
-        )));
-        self.chunks[chunk_index].2.context.clear_rect(
-            (x + 5) as f64 * 16.0,
-            y as f64 * 16.0,
-            16.0,
-            16.0,
-        );
-        self.chunks[chunk_index].2.context.fill_rect(
-            (x + 5) as f64 * 16.0,
-            y as f64 * 16.0,
-            16.0,
-            16.0,
-        );
-    }
-
-    #[cfg(target_arch = "wasm32")]
-    pub fn render_changes(&mut self, player: &Player) {
-        let player_x = player.x.floor() as isize;
-        let player_y = player.y.floor() as isize;
-        
-
-        for _idx in 0..std::cmp::min(self.light_to_render.len(), 50) {
-            let (x, y) = self.light_to_render.remove(0);
-            self.render_light(x, y);
-        }
-    }
-
-    #[cfg(target_arch = "wasm32")]
-    pub fn update_chunks(&mut self, player: &Player) {
-        let chunk_number = x_to_chunk(player.x.floor() as isize);
-
-        let mut diff = self.first_chunk_number - chunk_number;
-
-        while diff > -4 {
-            let mut chunk_canvas = Canvas::new();
-            chunk_canvas.set_width(42 * 16);
-            chunk_canvas.set_height(2048 * 16);
-
-            let mut light_chunk_canvas = Canvas::new();
-            light_chunk_canvas.set_width(42 * 16);
-            light_chunk_canvas.set_height(2048 * 16);
-
-            self.chunks.remove(self.chunks.len() - 1);
-            let mut config = self.chunks[0].0.left_config;
-            self.first_chunk_number -= 1;
-            self.chunks.insert(
-                0,
-                (
-                    Chunk::generate(
-                        &mut config.0,
-                        &mut config.1,
-                        false,
-                        self.first_chunk_number * 32,
-                    ),
-                    chunk_canvas,
-                    light_chunk_canvas
-                ),
-            );
-            self.chunks[0]
-                .1
-                .context
-                .set_fill_style(&wasm_bindgen::JsValue::from_str("rgb(135,206,235)"));
-            self.init_water(1);
-            let old_lights: Vec<(isize, isize, bool)> = self.light_update.drain(..).collect();
-            self.init_lights();
-            for x in (1 + self.first_chunk_number) * 32..(2 + self.first_chunk_number) * 32 {
-                for y in 0..100 {
-                    self.render_block(x, y);
-                    self.render_light(x, y);
-                }
+        let corners = world.get_corner_lights((x, y));
+        let step = 16.0 / SMOOTH_LIGHT_STEPS as f64;
+        for i in 0..SMOOTH_LIGHT_STEPS {
+            for j in 0..SMOOTH_LIGHT_STEPS {
+                let center = ((i as f64 + 0.5) / SMOOTH_LIGHT_STEPS as f64, (j as f64 + 0.5) / SMOOTH_LIGHT_STEPS as f64);
+                layer.fill_rect(
+                    (column * 16.0 + i as f64 * step, row * 16.0 + j as f64 * step),
+                    (step, step),
+                    &light_to_color(interpolate_corners(corners, center)),
+                );
             }
-            self.light_update = old_lights;
-
-            diff = self.first_chunk_number - chunk_number;
         }
-        while diff < -4 {
-            let mut chunk_canvas = Canvas::new();
-            chunk_canvas.set_width(42 * 16);
-            chunk_canvas.set_height(2048 * 16);
-
-            let mut light_chunk_canvas = Canvas::new();
-            light_chunk_canvas.set_width(42 * 16);
-            light_chunk_canvas.set_height(2048 * 16);
+    }
 
-            self.chunks.remove(0);
-            let mut config = self.chunks[self.chunks.len() - 1].0.right_config;
-            self.first_chunk_number += 1;
-            self.chunks.push((
-                Chunk::generate(
-                    &mut config.0,
-                    &mut config.1,
-                    true,
-                    self.first_chunk_number * 32,
-                ),
-                chunk_canvas,
-                light_chunk_canvas
-            ));
-            let idx = self.chunks.len() - 1;
-            self.chunks[idx]
-                .1
-                .context
-                .set_fill_style(&wasm_bindgen::JsValue::from_str("rgb(135,206,235)"));
-            let old_lights: Vec<(isize, isize, bool)> = self.light_update.drain(..).collect();
-            self.init_lights();
-            self.init_water(idx - 1);
-            for x in (idx as isize + self.first_chunk_number) * 32 - 32..(idx as isize + self.first_chunk_number) * 32 {
-                for y in 0..100 {
-                    self.render_block(x, y);
-                    self.render_light(x, y);
+    /// Renders the light of a block which changed, and of its neighbours if their corners depend on it.
+    fn render_light_change(&mut self, world: &World, x: isize, y: isize) {
+        match self.smooth_lighting {
+            false => self.render_light(world, x, y),
+            true => {
+                for (x, y) in (x - 1..=x + 1).flat_map(|x| (y - 1..=y + 1).map(move |y| (x, y))) {
+                    self.render_light(world, x, y);
                 }
             }
-            self.light_update = old_lights;
-
-            diff = self.first_chunk_number - chunk_number;
         }
+    }
 
-        let total_changes = self.light_to_render.len();
-        if total_changes > 3000 {
-            wasm_game_lib::log!("WARNING: {} elements to render", total_changes);
+    /// Switches between flat and smooth lighting and renders the light of the rendered sections again.
+    pub fn set_smooth_lighting(&mut self, world: &World, smooth_lighting: bool) {
+        if self.smooth_lighting == smooth_lighting {
+            return;
         }
-
-        self.render_changes(player);
+        self.smooth_lighting = smooth_lighting;
+        self.render_lights(world, |_, _| true);
     }
 
-    pub fn init_lights(&mut self) {
-        self.light_update.clear();
+    pub fn is_lighting_smooth(&self) -> bool {
+        self.smooth_lighting
+    }
 
-        for x in self.first_chunk_number * 32
-            ..(self.first_chunk_number + self.chunks.len() as isize) * 32
-        {
-            let mut need_spreading = false;
-            for y in 0..2 {
-                if y == 0 && self[(x, y)].light == 0 {
-                    self.index_mut_and_render((x, y)).light = 100;
-                    need_spreading = true;
+    /// Renders the light of the blocks of the rendered sections for which `filter` returns true.
+    fn render_lights(&mut self, world: &World, filter: impl Fn(isize, isize) -> bool) {
+        for chunk_index in 0..self.chunks.len() {
+            for section in 0..SECTION_COUNT {
+                if self.chunks[chunk_index][section].is_none() {
+                    continue;
                 }
-
-                if need_spreading {
-                    self.light_update.push((x, y, false));
+                let x0 = (self.first_chunk_number + chunk_index as isize) * 32;
+                let y0 = (section * SECTION_SIZE) as isize;
+                for x in x0..x0 + SECTION_SIZE as isize {
+                    for y in y0..y0 + SECTION_SIZE as isize {
+                        if filter(x, y) {
+                            self.render_light(world, x, y);
+                        }
+                    }
                 }
             }
         }
-
-        self.spread_lights();
     }
 
-    pub fn spread_lights(&mut self) {
-        let mut n = 0;
-        while !self.light_update.is_empty() {
-            self.update_light();
-            n += 1;
-
-            if n > 100000 {
-                panic!("100000 lights spread. Program hanging");
-            }
+    /// Follows the chunks loaded by the world, renders the sections coming close to the player
+    /// and what changed since the last call.
+    pub fn update(&mut self, world: &mut World, player: &Player) {
+        while self.first_chunk_number > world.first_chunk_number {
+            self.chunks.remove(self.chunks.len() - 1);
+            self.first_chunk_number -= 1;
+            self.chunks.insert(0, Map::new_chunk());
         }
-    }
-
-    fn update_light(&mut self) {
-        use std::cmp::max;
-
-        if self.light_update.is_empty() {
-            return;
+        while self.first_chunk_number < world.first_chunk_number {
+            self.chunks.remove(0);
+            self.first_chunk_number += 1;
+            self.chunks.push(Map::new_chunk());
+        }
+
+        let player_section = (player.y.floor() as isize).div_euclid(SECTION_SIZE as isize);
+        for chunk_index in 0..self.chunks.len() {
+            for section in 0..SECTION_COUNT {
+                let visible = (section as isize - player_section).abs() <= SECTION_DISTANCE
+                    && world.chunks[chunk_index].is_generated(section);
+                match (visible, self.chunks[chunk_index][section].is_some()) {
+                    (true, false) => {
+                        self.chunks[chunk_index][section] = Some(self.new_section_layers());
+                        let x0 = (self.first_chunk_number + chunk_index as isize) * 32;
+                        let y0 = (section * SECTION_SIZE) as isize;
+                        for x in x0..x0 + SECTION_SIZE as isize {
+                            for y in y0..y0 + SECTION_SIZE as isize {
+                                self.render_block(world, x, y);
+                                self.render_light(world, x, y);
+                            }
+                        }
+                        // the corners of the blocks around the section depend on it
+                        if self.smooth_lighting {
+                            let size = SECTION_SIZE as isize;
+                            for x in x0 - 1..=x0 + size {
+                                self.render_light(world, x, y0 - 1);
+                                self.render_light(world, x, y0 + size);
+                            }
+                            for y in y0..y0 + size {
+                                self.render_light(world, x0 - 1, y);
+                                self.render_light(world, x0 + size, y);
+                            }
+                        }
+                    }
+                    (false, true) => self.chunks[chunk_index][section] = None,
+                    _ => (),
+                }
+            }
         }
 
-        let (x, y, cancellation) = self.light_update.remove(0);
-        if x <= self.first_chunk_number * 32
-            || x >= (self.first_chunk_number + self.chunks.len() as isize) * 32
-            || y < 0
-            || y > 2048
-        {
-            return;
+        // sunlight changes color with the time of the day, smooth light also depends on the sunlight of the neighbours
+        if world.clock.daylight() != self.daylight {
+            self.daylight = world.clock.daylight();
+            let radius = self.smooth_lighting as isize;
+            self.render_lights(world, |x, y| {
+                (x - radius..=x + radius).any(|x| (y - radius..=y + radius).any(|y| world[(x, y)].light.sun > 0))
+            });
         }
 
-        if cancellation {
-            let updates;
-
-            {
-                let light = &self[(x, y)].light;
-                let left_block = &self[(x - 1, y)];
-                let right_block = &self[(x + 1, y)];
-                let top_block = &self[(x, y - 1)];
-                let bottom_block = &self[(x, y + 1)];
-                updates = (
-                    right_block.light + right_block.block_type.get_light_loss() == *light,
-                    left_block.light + left_block.block_type.get_light_loss() == *light,
-                    top_block.light + top_block.block_type.get_light_loss() == *light,
-                    bottom_block.light + bottom_block.block_type.get_light_loss() == *light,
-                );
-            }
-
-            self[(x, y)].light = 0;
-
-            if updates.0 && !self.light_update.contains(&(x + 1, y, true)) {
-                self.light_update.push((x + 1, y, true))
-            }
-            if updates.1 && !self.light_update.contains(&(x - 1, y, true)) {
-                self.light_update.push((x - 1, y, true))
-            }
-            if updates.2 && !self.light_update.contains(&(x, y - 1, true)) {
-                self.light_update.push((x, y - 1, true))
-            }
-            if updates.3 && !self.light_update.contains(&(x, y + 1, true)) {
-                self.light_update.push((x, y + 1, true))
-            }
-            return;
+        for (x, y) in world.blocks_to_render.drain(..).collect::<Vec<_>>() {
+            self.render_block(world, x, y);
         }
 
-        let light;
-        let updates;
-        {
-            let block = &self[(x, y)];
-            let left_block = &self[(x - 1, y)];
-            let right_block = &self[(x + 1, y)];
-            let top_block = &self[(x, y - 1)];
-            let bottom_block = &self[(x, y + 1)];
-            light = max(
-                max(left_block.light, right_block.light),
-                max(top_block.light, bottom_block.light),
-            )
-            .saturating_sub(block.block_type.get_light_loss());
-            updates = (
-                right_block.light + right_block.block_type.get_light_loss() < light,
-                left_block.light + left_block.block_type.get_light_loss() < light,
-                top_block.light + top_block.block_type.get_light_loss() < light,
-                bottom_block.light + bottom_block.block_type.get_light_loss() < light,
-            );
-        }
-        if updates.0 && !self.light_update.contains(&(x + 1, y, false)) {
-            self.light_update.push((x + 1, y, false))
-        }
-        if updates.1 && !self.light_update.contains(&(x - 1, y, false)) {
-            self.light_update.push((x - 1, y, false))
-        }
-        if updates.2 && !self.light_update.contains(&(x, y - 1, false)) {
-            self.light_update.push((x, y - 1, false))
-        }
-        if updates.3 && !self.light_update.contains(&(x, y + 1, false)) {
-            self.light_update.push((x, y + 1, false))
+        let total_changes = world.light_to_render.len();
+        if total_changes > 3000 {
+            wasm_game_lib::log!("WARNING: {} elements to render", total_changes);
         }
 
-        if self[(x, y)].light != light {
-            self[(x, y)].light = light;
-            self.light_to_render.push((x, y));
+        for _idx in 0..50 {
+            match world.light_to_render.pop_front() {
+                Some((x, y)) => self.render_light_change(world, x, y),
+                None => break,
+            }
         }
     }
 
-    #[cfg(target_arch = "wasm32")]
-    pub fn draw_on_canvas<'a>(
-        &'a mut self,
-        canvas: &'a mut Canvas,
+    pub fn draw_on_canvas(
+        &mut self,
+        canvas: &mut R,
+        world: &World,
         player: &Player,
         screen_center: (isize, isize),
     ) {
-        canvas.clear();
-        let gradient = canvas
-            .context
-            .create_radial_gradient(
-                screen_center.0 as f64,
-                screen_center.1 as f64 - 50.0,
-                50.0,
-                screen_center.0 as f64,
-                screen_center.1 as f64 - 50.0,
-                500.0,
-            )
-            .unwrap();
-        gradient.add_color_stop(0.0, "rgba(0, 0, 0, 1.0)").unwrap();
-        gradient.add_color_stop(0.5, "rgba(0, 0, 0, 0.2)").unwrap();
-        gradient.add_color_stop(1.0, "rgba(0, 0, 0, 0.0)").unwrap();
-        canvas.context.set_fill_style(&gradient);
-        canvas.context.fill_rect(
-            0.0,
-            0.0,
-            canvas.get_width() as f64,
-            canvas.get_height() as f64,
-        );
+        let (width, height) = canvas.get_size();
+        if self.canvas.get_size() != (width, height) {
+            self.canvas = canvas.create_layer(width, height);
+            self.light = canvas.create_layer(width, height);
+        }
 
         let (mut screen_x, mut screen_y) = map_to_screen(
             self.first_chunk_number * 32,
-            self.first_block as isize,
-            &player,
+            0,
+            player,
             screen_center,
         );
         screen_x = screen_x.floor();
         screen_y = screen_y.floor();
 
-        for (chunk_idx, light_canvas) in self.chunks.iter().map(|(_a, _b, c)| c).enumerate() {
-            canvas.draw_canvas((screen_x + chunk_idx as f64 * 32.0 * 16.0 - 5.0 * 16.0, screen_y), &light_canvas);
+        let section_size = SECTION_SIZE as f64 * 16.0;
+        self.light.clear();
+        self.light.fill_rect((0.0, 0.0), (width as f64, height as f64), &Color::black());
+        for (chunk_idx, sections) in self.chunks.iter().enumerate() {
+            for (section, (_, light_canvas)) in sections.iter().enumerate().filter_map(|(idx, layers)| Some((idx, layers.as_ref()?))) {
+                self.light.draw_layer(
+                    light_canvas,
+                    (screen_x + chunk_idx as f64 * section_size, screen_y + section as f64 * section_size),
+                );
+            }
         }
+        // the player always sees a bit around them
+        self.light.set_composite_mode(CompositeMode::Screen);
+        self.light.fill_radial_gradient(
+            (screen_center.0 as f64, screen_center.1 as f64 - 50.0),
+            (50.0, 500.0),
+            &[
+                (0.0, Color::new_with_alpha(255, 255, 255, 255)),
+                (0.5, Color::new_with_alpha(255, 255, 255, 51)),
+                (1.0, Color::new_with_alpha(255, 255, 255, 0)),
+            ],
+        );
+        self.light.set_composite_mode(CompositeMode::SourceOver);
 
         self.canvas.clear();
-        for (chunk_idx, chunk_canvas) in self.chunks.iter().map(|(_a, b, _c)| b).enumerate() {
-            self.canvas.draw_canvas(
-                (
-                    (chunk_idx as f64 * 32.0 * 16.0) - 5.0 * 16.0,
-                    self.first_block as f64 * 16.0,
-                ),
-                &chunk_canvas,
-            );
+        for (chunk_idx, sections) in self.chunks.iter().enumerate() {
+            for (section, (block_canvas, _)) in sections.iter().enumerate().filter_map(|(idx, layers)| Some((idx, layers.as_ref()?))) {
+                self.canvas.draw_layer(
+                    block_canvas,
+                    (
+                        screen_x + chunk_idx as f64 * section_size,
+                        screen_y + section as f64 * section_size,
+                    ),
+                );
+            }
         }
 
-        let linestyle: LineStyle = LineStyle {
-            cap: LineCap::Square,
-            color: Color::blue(),
-            size: 3.0,
-            join: LineJoin::Bevel,
-        };
-        linestyle.apply_on_canvas(&mut self.canvas);
-        self.canvas.context.set_fill_style(&JsValue::from("blue"));
-
-        #[allow(clippy::collapsible_if)]
-        for y in 0..100 {
-            let mut begin_path = false;
-            for x in player.x.floor() as isize - 60..player.x.floor() as isize + 60 {
-                if self[(x, y)].water > 0.0 || self[(x - 1, y)].water > 0.0 || self[(x + 1, y)].water > 0.0 {
-                    let mut level = self[(x, y)].water.floor();
-                    if level > 16.0 {
-                        level = 16.0;
-                    }
-
-                    if !begin_path {
-                        self.canvas.context.begin_path();
-                        if self[(x, y)].water == 0.0 {
-                            self.canvas.context.move_to((x as f64 * 16.0 + 16.0) - self.first_chunk_number as f64 * 32.0 * 16.0, y as f64 * 16.0 + 16.0);
+        // falling blocks are only drawn when they have an atlas texture, with all their borders
+        for block in &world.falling_blocks {
+            if let Some(BlockTexture::Atlas { atlas }) = &block.block_type.get_properties().texture {
+                let (x, top) = map_to_screen(block.x, 0, player, screen_center);
+                self.canvas.draw_sprite_part(
+                    Sprite::Blocks,
+                    ((atlas.0 as usize + get_texture_idx((true, true, true, true))) as f64 * 16.0, atlas.1 as f64 * 16.0),
+                    (16.0, 16.0),
+                    (x.floor(), (top + block.y * 16.0).floor()),
+                );
+            }
+        }
 
-                            if !self[(x, y)].block_type.can_pass_through() {
-                                let mut right_level = self[(x + 1, y)].water.floor();
-                                if right_level > 16.0 {
-                                    right_level = 16.0;
+        // fluid paths are computed relatively to the first loaded chunk
+        let offset = self.first_chunk_number as f64 * 32.0 * 16.0;
+        let player_row = player.y.floor() as isize;
+        for (fluid, line_style) in [(Fluid::Water, &WATER_LINE_STYLE), (Fluid::Lava, &LAVA_LINE_STYLE)] {
+            let fluid_level = |position: (isize, isize)| match world[position].fluid == fluid {
+                true => world[position].fluid_level,
+                false => 0,
+            };
+            for y in std::cmp::max(player_row - 40, 0)..player_row + 40 {
+                let mut path: Option<Vec<(f64, f64)>> = None;
+                for x in player.x.floor() as isize - 60..player.x.floor() as isize + 60 {
+                    if fluid_level((x, y)) >= VISIBLE_FLUID || fluid_level((x - 1, y)) >= VISIBLE_FLUID || fluid_level((x + 1, y)) >= VISIBLE_FLUID {
+                        let level = fluid_height(fluid_level((x, y)));
+
+                        match &mut path {
+                            None => {
+                                let mut points = Vec::new();
+                                if fluid_level((x, y)) < VISIBLE_FLUID {
+                                    points.push(((x as f64 * 16.0 + 16.0) - offset, y as f64 * 16.0 + 16.0));
+
+                                    if !world[(x, y)].block_type.can_pass_through() {
+                                        let right_level = fluid_height(fluid_level((x + 1, y)));
+
+                                        points.push(((x as f64 * 16.0 + 16.0) - offset, y as f64 * 16.0 + 16.0 - right_level));
+                                    }
+                                } else {
+                                    points.push(((x as f64 * 16.0 + 8.0) - offset, y as f64 * 16.0 + 16.0 - level));
                                 }
+                                path = Some(points);
+                            }
+                            Some(points) => {
+                                if fluid_level((x, y)) < VISIBLE_FLUID {
+                                    if !world[(x, y)].block_type.can_pass_through() {
+                                        let left_level = fluid_height(fluid_level((x - 1, y)));
 
-                                self.canvas.context.line_to((x as f64 * 16.0 + 16.0) - self.first_chunk_number as f64 * 32.0 * 16.0, y as f64 * 16.0 + 16.0 - right_level);
+                                        points.push(((x as f64 * 16.0) - offset, y as f64 * 16.0 + 16.0 - left_level));
+                                    }
+
+                                    points.push(((x as f64 * 16.0) - offset, y as f64 * 16.0 + 16.0));
+                                } else {
+                                    points.push(((x as f64 * 16.0 + 8.0) - offset, y as f64 * 16.0 + 16.0 - level));
+                                }
                             }
-                        } else {
-                            self.canvas.context.move_to((x as f64 * 16.0 + 8.0) - self.first_chunk_number as f64 * 32.0 * 16.0, y as f64 * 16.0 + 16.0 - level);
                         }
-                        begin_path = true;
-                    } else {
-                        if self[(x, y)].water == 0.0 {
-                            if !self[(x, y)].block_type.can_pass_through() {
-                                let mut left_level = self[(x - 1, y)].water.floor();
-                                if left_level > 16.0 {
-                                    left_level = 16.0;
-                                }
 
-                                self.canvas.context.line_to((x as f64 * 16.0) - self.first_chunk_number as f64 * 32.0 * 16.0, y as f64 * 16.0 + 16.0 - left_level);
+                        if fluid_level((x, y)) < VISIBLE_FLUID && fluid_level((x + 1, y)) < VISIBLE_FLUID {
+                            if let Some(points) = path.take() {
+                                let points: Vec<(f64, f64)> = points.iter().map(|(x, y)| (x + screen_x, y + screen_y)).collect();
+                                self.canvas.draw_path(&points, Some(&line_style.color), Some(line_style));
                             }
-
-                            self.canvas.context.line_to((x as f64 * 16.0) - self.first_chunk_number as f64 * 32.0 * 16.0, y as f64 * 16.0 + 16.0);
-                        } else {
-                            self.canvas.context.line_to((x as f64 * 16.0 + 8.0) - self.first_chunk_number as f64 * 32.0 * 16.0, y as f64 * 16.0 + 16.0 - level);
                         }
                     }
-
-                    if begin_path && self[(x, y)].water == 0.0 && self[(x + 1, y)].water == 0.0 {
-                        self.canvas.context.close_path();
-                        self.canvas.context.stroke();
-                        self.canvas.context.fill();
-                        begin_path = false;
-                    }
-                }
-            }
-        }
-
-        canvas
-            .context
-            .set_global_composite_operation("source-in")
-            .unwrap();
-        canvas.draw_canvas((screen_x, screen_y), &self.canvas);
-        canvas
-            .context
-            .set_global_composite_operation("destination-over")
-            .unwrap();
-        use wasm_bindgen::JsValue;
-        canvas
-            .context
-            .set_fill_style(&JsValue::from_str(&Color::black().to_string()));
-        canvas.context.fill_rect(
-            0.0,
-            0.0,
-            screen_center.0 as f64 * 2.0,
-            screen_center.1 as f64 * 2.0 + 1.0,
-        );
-        canvas
-            .context
-            .set_global_composite_operation("source-over")
-            .unwrap();
-    }
-
-    pub fn index_mut_and_render(&mut self, (x, y): (isize, isize)) -> &mut Block {
-        self.render_block(x, y);
-        self.render_block(x, y - 1); // TODO fix -1
-        self.render_block(x, y + 1);
-        self.render_block(x - 1, y);
-        self.render_block(x + 1, y);
-        &mut self[(x, y)]
-    }
-
-    pub fn init_water(&mut self, chunk_index: usize) {
-        for x in 0..32 {
-            for y in 0..2048 {
-                if self.chunks[chunk_index].0.blocks[x][y].water > 0.0 {
-                    self.water_update.push((x as isize + (self.first_chunk_number + chunk_index as isize) * 32, y as isize));
                 }
             }
         }
-    }
-
-    #[allow(clippy::collapsible_if)]
-    pub fn flow_water(&mut self) {
-        fn get_pressure(level:f64) -> f64 {
-            match level {
-                0.0..=16.0 => 16.0,
-                16.0..=17.0 => 17.0,
-                17.0..=18.0 => 18.0,
-                18.0..=19.0 => 19.0,
-                19.0..=20.0 => 20.0,
-                _ => 21.0,
-            }
-        }
-
-        for (x, y) in self.water_update.clone() {
-            if self[(x, y)].water > 0.1 {
-                let mut dont_continue = false;
-                if self[(x, y + 1)].block_type == BlockType::Air {
-                    let mut quantity = if self[(x, y)].water > 1.0 {
-                        1.0
-                    } else {
-                        self[(x, y)].water
-                    };
-                    if self[(x, y + 1)].water >= 16.0 {
-                        let max = get_pressure(self[(x, y)].water);
-                        let max = (max + 1.0) - self[(x, y + 1)].water;
-                        if quantity > max {
-                            quantity = max;
-                            dont_continue = true;
-                        }
-                    }
-                    self[(x, y + 1)].water += quantity;
-                    self[(x, y)].water -= quantity;
 
-                    if !self.water_update.contains(&(x, y + 1)) {
-                        self.water_update.push((x, y + 1));
-                    }
-
-                    if !dont_continue {
-                        continue;
-                    }
-                }
-
-                // TODO remove 5
-                /*if y > 5 && self[(x, y - 1)].block_type.can_pass_through() && self[(x, y)].water > 16.0 && self[(x, y - 1)].water + 1.0 < self[(x, y)].water {
-                    log!("so much pressure!");
-                    self[(x, y - 1)].water += 1.0;
-                    self[(x, y)].water -= 1.0;
-
-                    if !self.water_update.contains(&(x, y - 1)) {
-                        self.water_update.push((x, y - 1));
-                    }
-                }*/
-
-                let left =  self[(x - 1, y)].block_type == BlockType::Air;
-                let right = self[(x + 1, y)].block_type == BlockType::Air;
-
-                let water = match (left, right) {
-                    (true, true) => (self[(x, y)].water + self[(x - 1, y)].water + self[(x + 1, y)].water) / 3.0,
-                    (true, false) => (self[(x, y)].water + self[(x - 1, y)].water)  / 2.0,
-                    (false, true) => (self[(x, y)].water + self[(x + 1, y)].water) / 2.0,
-                    (false, false) => continue,
-                };
-
-                self[(x, y)].water = water;
-                if left {
-                    self[(x - 1, y)].water = water;
-                    if !self.water_update.contains(&(x - 1, y)) {
-                        self.water_update.push((x - 1, y));
-                    }
-                }
-                if right {
-                    self[(x + 1, y)].water = water;
-                    if !self.water_update.contains(&(x + 1, y)) {
-                        self.water_update.push((x + 1, y));
-                    }
-                }
-            } else {
-                self[(x, y)].water = 0.0;
-                self.water_update.retain(|e| e != &(x,y));
-            }
-        }
+        canvas.clear();
+        canvas.draw_layer(&self.canvas, (0.0, 0.0));
+        canvas.set_composite_mode(CompositeMode::DestinationOver);
+        let (top, horizon) = sky_colors(&world.clock);
+        canvas.fill_linear_gradient((0.0, 0.0), (0.0, height as f64), &[(0.0, top), (1.0, horizon)]);
+        canvas.set_composite_mode(CompositeMode::Multiply);
+        canvas.draw_layer(&self.light, (0.0, 0.0));
+        canvas.set_composite_mode(CompositeMode::SourceOver);
     }
 }
 
-impl std::ops::Index<(isize, isize)> for Map {
-    type Output = Block;
-
-    fn index(&self, (x, y): (isize, isize)) -> &Self::Output {
-        let (chunk, column) = x_to_chunk_and_column(x);
-        let chunk_index = chunk - self.first_chunk_number;
-
-        if y >= 0 && chunk_index >= 0 {
-            if let Some(chunk) = self.chunks.get(chunk_index as usize) {
-                if let Some(block) = chunk.0.blocks[column as usize].get(y as usize) {
-                    return &block;
-                }
-            }
-        }
-
-        &Block {
-            block_type: BlockType::Air,
-            natural_background: NaturalBackground::Sky,
-            light: 0,
-            water: 0.0,
-        }
-    }
+fn mix(a: &Color, b: &Color, t: f64) -> Color {
+    let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    Color::new(channel(a.red, b.red), channel(a.green, b.green), channel(a.blue, b.blue))
 }
 
-impl std::ops::IndexMut<(isize, isize)> for Map {
-    fn index_mut(&mut self, (x, y): (isize, isize)) -> &mut Self::Output {
-        let (chunk_number, column) = x_to_chunk_and_column(x);
-        let chunk_index = chunk_number - self.first_chunk_number;
-
-        if y >= 0 && chunk_index >= 0 {
-            if let Some(chunk) = self.chunks.get_mut(chunk_index as usize) {
-                if let Some(block) = chunk.0.blocks[column as usize].get_mut(y as usize) {
-                    return block;
-                }
-            }
-        }
-
-        if self.air
-            != (Block {
-                block_type: BlockType::Air,
-                natural_background: NaturalBackground::Sky,
-                light: 0,
-                water: 0.0,
-            })
-        {
-            self.air = Block {
-                block_type: BlockType::Air,
-                natural_background: NaturalBackground::Sky,
-                light: 0,
-                water: 0.0,
-            };
-        }
-
-        &mut self.air
-    }
-}
\ No newline at end of file
+/// Colors of the top of the sky and of the horizon at the time of the day of a clock.
+pub fn sky_colors(clock: &Clock) -> (Color, Color) {
+    let sun_height = clock.sun_height();
+    let top = mix(&NIGHT_SKY.0, &DAY_SKY.0, sun_height);
+    let horizon = mix(&NIGHT_SKY.1, &DAY_SKY.1, sun_height);
+    // the horizon is the most colored when the sun is half risen
+    let twilight = 1.0 - (2.0 * sun_height - 1.0).abs();
+    (top, mix(&horizon, &TWILIGHT, twilight * 0.7))
+}
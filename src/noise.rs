@@ -0,0 +1,118 @@
+//! Value noise: random values on an integer lattice, smoothly interpolated in between.
+//! Everything is a pure function of the seed and the coordinates so chunks can be generated in any order.
+
+use crate::seed::WorldSeed;
+use std::hash::Hasher;
+
+/// A random value in `[-1, 1]` for a point of the lattice.
+/// The `layer` separates independent noises generated from the same seed.
+pub fn lattice_value(seed: WorldSeed, layer: u32, x: i64, y: i64) -> f64 {
+    let mut hasher = seed.hasher();
+    hasher.write_u32(layer);
+    hasher.write_i64(x);
+    hasher.write_i64(y);
+    hasher.finish() as f64 / u32::MAX as f64 * 2.0 - 1.0
+}
+
+fn smoothstep(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Noise in `[-1, 1]` varying once per unit of `x`.
+pub fn noise_1d(seed: WorldSeed, layer: u32, x: f64) -> f64 {
+    let x0 = x.floor();
+    let t = smoothstep(x - x0);
+    lerp(
+        lattice_value(seed, layer, x0 as i64, 0),
+        lattice_value(seed, layer, x0 as i64 + 1, 0),
+        t,
+    )
+}
+
+/// Noise in `[-1, 1]` varying once per unit in both directions.
+pub fn noise_2d(seed: WorldSeed, layer: u32, x: f64, y: f64) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (smoothstep(x - x0), smoothstep(y - y0));
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    lerp(
+        lerp(lattice_value(seed, layer, x0, y0), lattice_value(seed, layer, x0 + 1, y0), tx),
+        lerp(lattice_value(seed, layer, x0, y0 + 1), lattice_value(seed, layer, x0 + 1, y0 + 1), tx),
+        ty,
+    )
+}
+
+/// Sums `octaves` layers of noise, each with twice the frequency and half the amplitude of the previous one.
+/// The result stays in `[-1, 1]`.
+pub fn fractal_noise_1d(seed: WorldSeed, layer: u32, x: f64, octaves: u32) -> f64 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut amplitudes = 0.0;
+    for octave in 0..octaves {
+        total += noise_1d(seed, layer.wrapping_add(octave * 1000), x * (1 << octave) as f64) * amplitude;
+        amplitudes += amplitude;
+        amplitude /= 2.0;
+    }
+    total / amplitudes
+}
+
+/// Same as `fractal_noise_1d` in two dimensions.
+pub fn fractal_noise_2d(seed: WorldSeed, layer: u32, x: f64, y: f64, octaves: u32) -> f64 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut amplitudes = 0.0;
+    for octave in 0..octaves {
+        let frequency = (1 << octave) as f64;
+        total += noise_2d(seed, layer.wrapping_add(octave * 1000), x * frequency, y * frequency) * amplitude;
+        amplitudes += amplitude;
+        amplitude /= 2.0;
+    }
+    total / amplitudes
+}
+
+/// Computes `noise_2d(seed, layer, x / scale.0, y / scale.1)` for every block of an area, row by row.
+/// Lattice values are computed once for the whole area, which is much faster than calling `noise_2d` for each block.
+pub fn noise_2d_area(
+    seed: WorldSeed,
+    layer: u32,
+    (x, y): (isize, isize),
+    (width, height): (usize, usize),
+    (scale_x, scale_y): (f64, f64),
+) -> Vec<f64> {
+    let lattice_x0 = (x as f64 / scale_x).floor() as i64;
+    let lattice_y0 = (y as f64 / scale_y).floor() as i64;
+    let lattice_width = ((x + width as isize) as f64 / scale_x).floor() as i64 - lattice_x0 + 2;
+    let lattice_height = ((y + height as isize) as f64 / scale_y).floor() as i64 - lattice_y0 + 2;
+    let mut lattice = Vec::with_capacity((lattice_width * lattice_height) as usize);
+    for lattice_y in lattice_y0..lattice_y0 + lattice_height {
+        for lattice_x in lattice_x0..lattice_x0 + lattice_width {
+            lattice.push(lattice_value(seed, layer, lattice_x, lattice_y));
+        }
+    }
+    let get = |lattice_x: i64, lattice_y: i64| {
+        lattice[((lattice_y - lattice_y0) * lattice_width + lattice_x - lattice_x0) as usize]
+    };
+
+    let mut values = Vec::with_capacity(width * height);
+    for block_y in y..y + height as isize {
+        let noise_y = block_y as f64 / scale_y;
+        let y0 = noise_y.floor();
+        let ty = smoothstep(noise_y - y0);
+        let y0 = y0 as i64;
+        for block_x in x..x + width as isize {
+            let noise_x = block_x as f64 / scale_x;
+            let x0 = noise_x.floor();
+            let tx = smoothstep(noise_x - x0);
+            let x0 = x0 as i64;
+            values.push(lerp(
+                lerp(get(x0, y0), get(x0 + 1, y0), tx),
+                lerp(get(x0, y0 + 1), get(x0 + 1, y0 + 1), tx),
+                ty,
+            ));
+        }
+    }
+    values
+}
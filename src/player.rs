@@ -1,6 +1,10 @@
-use crate::{items::*, map::Map, textures::Textures};
-use std::rc::Rc;
-use wasm_bindgen::JsValue;
+use crate::{
+    fluid::{Fluid, FLUID_FULL},
+    items::*,
+    renderer::Renderer,
+    textures::Sprite,
+    world::World,
+};
 use wasm_game_lib::graphics::{canvas::*, color::Color};
 
 const INVENTORY_BORDER_STYLE: LineStyle = LineStyle {
@@ -26,147 +30,231 @@ const SELECTED_INVENTORY_BORDER_STYLE: LineStyle = LineStyle {
     size: 4.0,
 };
 
+const INVENTORY_BACKGROUND_COLOR: Color = Color {
+    red: 24,
+    green: 28,
+    blue: 39,
+    alpha: 230,
+};
+const SLOT_BACKGROUND_COLOR: Color = Color {
+    red: 255,
+    green: 255,
+    blue: 255,
+    alpha: 59,
+};
+const BREATH_COLOR: Color = Color {
+    red: 120,
+    green: 200,
+    blue: 255,
+    alpha: 230,
+};
+
+/// Seconds the player can stay with the head under water.
+pub const MAX_BREATH: f64 = 10.0;
+/// Seconds of breath recovered per second out of water.
+const BREATH_RECOVERY: f64 = 4.0;
+const GRAVITY: f64 = 0.02;
+/// Upward acceleration of a fully submerged player, a bit stronger than gravity so that the player floats.
+const BUOYANCY: f64 = 0.03;
+/// Part of the vertical speed kept on each frame when fully submerged.
+const WATER_DRAG: f64 = 0.85;
+/// Horizontal speed in water compared to the speed on the ground.
+const SWIMMING_SPEED: f64 = 0.5;
+/// Upward acceleration given by the jump key in water, and the speed it can reach.
+const SWIM_STRENGTH: f64 = 0.04;
+const MAX_SWIM_SPEED: f64 = 0.2;
+/// Where the player appears in a new world and after drowning, over the surface.
+const SPAWN: (f64, f64) = (3.0, -10.0);
+
 pub struct Player {
     pub x: f64,
     pub y: f64,
     speed_y: f64,
-    textures: Rc<Textures>,
     last_frame_running: usize,
     to_left: bool,
     is_inventory_open: bool,
     pub selected_slot: u8,
     pub inventory: Inventory,
+    /// Seconds left before drowning, `MAX_BREATH` out of water.
+    pub breath: f64,
+    /// The block being mined and the seconds spent on it.
+    mining: Option<((isize, isize), f64)>,
 }
 
 impl Player {
-    pub fn new(textures: Rc<Textures>) -> Player {
+    pub fn new() -> Player {
+        let mut inventory = Inventory::new(27);
+        // enough torches to explore the first caves
+        for _ in 0..16 {
+            inventory.push(Item::from_name("torch"));
+        }
+
         Player {
-            x: 3.0,
-            y: -10.0,
+            x: SPAWN.0,
+            y: SPAWN.1,
             speed_y: 0.0,
-            textures,
             last_frame_running: 0,
             to_left: true,
             is_inventory_open: false,
-            inventory: Inventory::new(27),
+            inventory,
             selected_slot: 0,
+            breath: MAX_BREATH,
+            mining: None,
         }
     }
 
-    pub fn is_touching_the_surface(&self, map: &Map) -> bool {
-        !map[(self.x.floor() as isize, (self.y + 0.03).floor() as isize)].can_pass_through()
-            || !map[(
+    pub fn is_touching_the_surface(&self, world: &World) -> bool {
+        !world[(self.x.floor() as isize, (self.y + 0.03).floor() as isize)].can_pass_through()
+            || !world[(
                 self.x.floor() as isize + 1,
                 (self.y + 0.03).floor() as isize,
             )]
                 .can_pass_through()
     }
 
-    pub fn is_under_the_surface(&self, map: &Map) -> bool {
-        !map[(self.x.floor() as isize, self.y.floor() as isize)].can_pass_through()
-            || !map[(self.x.floor() as isize + 1, self.y.floor() as isize)].can_pass_through()
+    pub fn is_under_the_surface(&self, world: &World) -> bool {
+        !world[(self.x.floor() as isize, self.y.floor() as isize)].can_pass_through()
+            || !world[(self.x.floor() as isize + 1, self.y.floor() as isize)].can_pass_through()
+    }
+
+    /// Blocks covered by the body of the player, two columns and seven rows up from the feet.
+    fn body_blocks(&self) -> impl Iterator<Item = (isize, isize)> {
+        let (x, y) = (self.x.floor() as isize, self.y.floor() as isize);
+        (x..x + 2).flat_map(move |x| (y - 6..=y).map(move |y| (x, y)))
+    }
+
+    /// Part of the body which is in water, from 0 to 1.
+    pub fn submersion(&self, world: &World) -> f64 {
+        let water: f64 = self
+            .body_blocks()
+            .filter(|position| world[*position].fluid == Fluid::Water)
+            .map(|position| world[position].fluid_level.min(FLUID_FULL) as f64 / FLUID_FULL as f64)
+            .sum();
+        water / 14.0
+    }
+
+    pub fn is_head_under_water(&self, world: &World) -> bool {
+        let (x, y) = (self.x.floor() as isize, self.y.floor() as isize - 6);
+        [(x, y), (x + 1, y)]
+            .iter()
+            .any(|position| world[*position].fluid == Fluid::Water && world[*position].fluid_level >= FLUID_FULL / 2)
+    }
+
+    pub fn is_drowning(&self) -> bool {
+        self.breath <= 0.0
     }
 
-    pub fn can_move_right_by(&self, distance: f64, map: &Map) -> bool {
-        map[(
+    /// Brings the player back to the spawn with full breath, keeping the inventory.
+    pub fn respawn(&mut self) {
+        self.x = SPAWN.0;
+        self.y = SPAWN.1;
+        self.speed_y = 0.0;
+        self.breath = MAX_BREATH;
+    }
+
+    pub fn can_move_right_by(&self, distance: f64, world: &World) -> bool {
+        world[(
             (self.x + distance).floor() as isize + 1,
             self.y.floor() as isize,
         )]
             .can_pass_through()
-            && map[(
+            && world[(
                 (self.x + distance).floor() as isize + 1,
                 self.y.floor() as isize - 1,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x + distance).floor() as isize + 1,
                 self.y.floor() as isize - 2,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x + distance).floor() as isize + 1,
                 self.y.floor() as isize - 3,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x + distance).floor() as isize + 1,
                 self.y.floor() as isize - 4,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x + distance).floor() as isize + 1,
                 self.y.floor() as isize - 5,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x + distance).floor() as isize + 1,
                 self.y.floor() as isize - 6,
             )]
                 .can_pass_through()
     }
 
-    pub fn can_move_left_by(&self, distance: f64, map: &Map) -> bool {
-        map[(
+    pub fn can_move_left_by(&self, distance: f64, world: &World) -> bool {
+        world[(
             (self.x - distance).floor() as isize,
             self.y.floor() as isize,
         )]
             .can_pass_through()
-            && map[(
+            && world[(
                 (self.x - distance).floor() as isize,
                 self.y.floor() as isize - 1,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x - distance).floor() as isize,
                 self.y.floor() as isize - 2,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x - distance).floor() as isize,
                 self.y.floor() as isize - 3,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x - distance).floor() as isize,
                 self.y.floor() as isize - 4,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x - distance).floor() as isize,
                 self.y.floor() as isize - 5,
             )]
                 .can_pass_through()
-            && map[(
+            && world[(
                 (self.x - distance).floor() as isize,
                 self.y.floor() as isize - 6,
             )]
                 .can_pass_through()
     }
 
-    pub fn can_move_up_by(&self, distance: f64, map: &Map) -> bool {
-        map[(
+    pub fn can_move_up_by(&self, distance: f64, world: &World) -> bool {
+        world[(
             self.x.floor() as isize,
             (self.y + distance).floor() as isize - 7,
         )]
             .can_pass_through()
-            && map[(
+            && world[(
                 self.x.floor() as isize + 1,
                 (self.y + distance).floor() as isize - 7,
             )]
                 .can_pass_through()
     }
 
-    pub fn handle_events(&mut self, keys: (bool, bool, bool, bool), map: &Map, frame: usize) {
+    pub fn handle_events(&mut self, keys: (bool, bool, bool, bool), world: &World, frame: usize) {
+        let submersion = self.submersion(world);
+        let speed_x = 0.15 * (1.0 - (1.0 - SWIMMING_SPEED) * submersion);
+
         if keys.1 {
-            if self.can_move_right_by(0.3, &map) {
-                self.x += 0.15;
+            if self.can_move_right_by(0.3, world) {
+                self.x += speed_x;
                 self.last_frame_running = frame;
                 self.to_left = false;
             }
 
-            if self.is_touching_the_surface(&map) && !self.can_move_right_by(0.9, &map) {
+            if self.is_touching_the_surface(world) && !self.can_move_right_by(0.9, world) {
                 self.y -= 1.0;
-                if self.can_move_right_by(0.9, &map) {
+                if self.can_move_right_by(0.9, world) {
                     self.speed_y = -0.29;
                     self.last_frame_running = frame;
                     self.to_left = false;
@@ -174,19 +262,21 @@ impl Player {
                 self.y += 1.0;
             }
         }
-        if keys.0 && self.is_touching_the_surface(&map) {
+        if keys.0 && submersion > 0.0 && !self.is_touching_the_surface(world) {
+            self.speed_y = (self.speed_y - SWIM_STRENGTH).max(-MAX_SWIM_SPEED);
+        } else if keys.0 && self.is_touching_the_surface(world) {
             self.speed_y = -0.45;
         }
         if keys.3 {
-            if self.can_move_left_by(0.3, &map) {
-                self.x -= 0.15;
+            if self.can_move_left_by(0.3, world) {
+                self.x -= speed_x;
                 self.last_frame_running = frame;
                 self.to_left = true;
             }
 
-            if self.is_touching_the_surface(&map) && !self.can_move_left_by(0.9, &map) {
+            if self.is_touching_the_surface(world) && !self.can_move_left_by(0.9, world) {
                 self.y -= 1.0;
-                if self.can_move_left_by(0.9, &map) {
+                if self.can_move_left_by(0.9, world) {
                     self.speed_y = -0.29;
                     self.last_frame_running = frame;
                     self.to_left = true;
@@ -196,7 +286,7 @@ impl Player {
         }
 
         if self.speed_y < 0.0 {
-            if self.can_move_up_by(self.speed_y, &map) {
+            if self.can_move_up_by(self.speed_y, world) {
                 self.y += self.speed_y;
             } else {
                 self.speed_y = 0.0;
@@ -205,22 +295,55 @@ impl Player {
             self.y += self.speed_y;
         }
 
-        if self.is_under_the_surface(&map) {
+        if self.is_under_the_surface(world) {
             self.y -= self.speed_y;
             self.y = self.y.ceil() - 0.01;
             self.speed_y = 0.0;
-        } else if !self.is_touching_the_surface(&map) {
-            self.speed_y += 0.02;
+        } else if !self.is_touching_the_surface(world) || BUOYANCY * submersion > GRAVITY {
+            self.speed_y += GRAVITY - BUOYANCY * submersion;
+            self.speed_y *= 1.0 - (1.0 - WATER_DRAG) * submersion;
+        }
+    }
+
+    /// Uses up breath while the head is under water and recovers it otherwise, over `elapsed` seconds.
+    /// The player respawns when running out of breath.
+    pub fn breathe(&mut self, world: &World, elapsed: f64) {
+        if self.is_head_under_water(world) {
+            self.breath = (self.breath - elapsed).max(0.0);
+        } else {
+            self.breath = (self.breath + BREATH_RECOVERY * elapsed).min(MAX_BREATH);
+        }
+        if self.is_drowning() {
+            self.respawn();
+        }
+    }
+
+    /// Keeps mining the block at a position. Returns true once it has been mined for as many seconds as its hardness.
+    pub fn mine(&mut self, position: (isize, isize), world: &World, elapsed: f64) -> bool {
+        let progress = match self.mining {
+            Some((mined, progress)) if mined == position => progress + elapsed,
+            _ => elapsed,
+        };
+        if progress >= world[position].block_type.get_hardness() {
+            self.mining = None;
+            true
+        } else {
+            self.mining = Some((position, progress));
+            false
         }
     }
 
+    pub fn stop_mining(&mut self) {
+        self.mining = None;
+    }
+
     pub fn change_inventory_state(&mut self) {
         self.is_inventory_open = !self.is_inventory_open;
     }
 
     pub fn draw_on_canvas(
         &mut self,
-        mut canvas: &mut Canvas,
+        canvas: &mut impl Renderer,
         screen_center: (isize, isize),
         mut frame: usize,
     ) {
@@ -229,139 +352,119 @@ impl Player {
             frame /= 12;
             frame %= 8;
             let x = frame as f64 * 96.0;
-            canvas
-                .get_2d_canvas_rendering_context()
-                .draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
-                    if self.to_left {
-                        &(self.textures.character.1).1
-                    } else {
-                        &(self.textures.character.1).0
-                    }
-                    .get_html_element(),
-                    x,
-                    0.0,
-                    96.0,
-                    128.0,
+            canvas.draw_sprite_part(
+                if self.to_left {
+                    Sprite::CharacterRunningLeft
+                } else {
+                    Sprite::CharacterRunning
+                },
+                (x, 0.0),
+                (96.0, 128.0),
+                (
                     screen_center.0 as f64 - 32.0,
                     screen_center.1 as f64 - 128.0,
-                    96.0,
-                    128.0,
-                )
-                .unwrap();
+                ),
+            );
         } else {
-            canvas
-                .get_2d_canvas_rendering_context()
-                .draw_image_with_html_image_element(
-                    if self.to_left {
-                        &(self.textures.character.0).1
-                    } else {
-                        &(self.textures.character.0).0
-                    }
-                    .get_html_element(),
+            canvas.draw_sprite(
+                if self.to_left {
+                    Sprite::CharacterIdleLeft
+                } else {
+                    Sprite::CharacterIdle
+                },
+                (
                     screen_center.0 as f64 - 32.0,
                     screen_center.1 as f64 - 128.0,
-                )
-                .unwrap();
+                ),
+            );
         }
 
-        INVENTORY_BORDER_STYLE.apply_on_canvas(&mut canvas);
-        canvas.context.begin_path();
-        canvas
-            .context
-            .set_fill_style(&JsValue::from_str("rgba(24, 28, 39, 0.9)"));
+        let mut borders = Vec::new();
 
         if self.is_inventory_open {
-            canvas.context.fill_rect(
-                0.0,
-                0.0,
-                screen_center.0 as f64 * 2.0 + 1.0,
-                screen_center.1 as f64 * 2.0 + 1.0,
+            canvas.fill_rect(
+                (0.0, 0.0),
+                (
+                    screen_center.0 as f64 * 2.0 + 1.0,
+                    screen_center.1 as f64 * 2.0 + 1.0,
+                ),
+                &INVENTORY_BACKGROUND_COLOR,
             );
-            canvas.context.stroke();
-
-            canvas
-                .context
-                .set_fill_style(&JsValue::from_str("rgba(255, 255, 255, 0.23)"));
 
             for idx in 0..27 {
                 let x = idx % 9;
                 let y = (idx - x) / 9;
-
-                canvas.context.fill_rect(
+                let position = (
                     104.0 + x as f64 * (64.0 + 40.0),
                     104.0 + y as f64 * (64.0 + 40.0),
-                    64.0,
-                    64.0,
                 );
 
-                canvas.context.rect(
-                    104.0 + x as f64 * (64.0 + 40.0),
-                    104.0 + y as f64 * (64.0 + 40.0),
-                    64.0,
-                    64.0,
-                );
+                canvas.fill_rect(position, (64.0, 64.0), &SLOT_BACKGROUND_COLOR);
+                borders.push(rect_path(position, (64.0, 64.0)));
 
                 if let Some((item, _quantity)) = self.inventory[idx] {
-                    canvas
-                        .context
-                        .draw_image_with_html_image_element(
-                            self.textures.get_for_item(item).get_html_element(),
-                            104.0 + x as f64 * (64.0 + 40.0),
-                            104.0 + y as f64 * (64.0 + 40.0),
-                        )
-                        .unwrap();
+                    canvas.draw_sprite(Sprite::Item(item), position);
                 }
             }
-            canvas.context.stroke();
+            for border in &borders {
+                canvas.draw_path(border, None, Some(&INVENTORY_BORDER_STYLE));
+            }
         } else {
-            canvas.context.fill_rect(
-                screen_center.0 as f64 - 4.5 * 64.0,
-                screen_center.1 as f64 * 2.0 - 64.0,
-                64.0 * 9.0,
-                64.0,
+            canvas.fill_rect(
+                (
+                    screen_center.0 as f64 - 4.5 * 64.0,
+                    screen_center.1 as f64 * 2.0 - 64.0,
+                ),
+                (64.0 * 9.0, 64.0),
+                &INVENTORY_BACKGROUND_COLOR,
             );
 
-            canvas
-                .context
-                .set_fill_style(&JsValue::from_str("rgba(255, 255, 255, 0.23)"));
-
             for x in 0..9 {
-                canvas.context.fill_rect(
+                let position = (
                     screen_center.0 as f64 - 4.5 * 64.0 + x as f64 * 64.0,
                     screen_center.1 as f64 * 2.0 - 64.0,
-                    64.0,
-                    64.0,
                 );
 
-                canvas.context.rect(
-                    screen_center.0 as f64 - 4.5 * 64.0 + x as f64 * 64.0,
-                    screen_center.1 as f64 * 2.0 - 64.0,
-                    64.0,
-                    64.0,
-                );
+                canvas.fill_rect(position, (64.0, 64.0), &SLOT_BACKGROUND_COLOR);
+                borders.push(rect_path(position, (64.0, 64.0)));
 
                 if let Some((item, _quantity)) = self.inventory[x as usize] {
-                    canvas
-                        .context
-                        .draw_image_with_html_image_element(
-                            self.textures.get_for_item(item).get_html_element(),
-                            screen_center.0 as f64 - 4.5 * 64.0 + x as f64 * 64.0,
-                            screen_center.1 as f64 * 2.0 - 64.0,
-                        )
-                        .unwrap();
+                    canvas.draw_sprite(Sprite::Item(item), position);
                 }
             }
-            canvas.context.stroke();
-
-            canvas.context.begin_path();
-            SELECTED_INVENTORY_BORDER_STYLE.apply_on_canvas(&mut canvas);
-            canvas.context.rect(
-                screen_center.0 as f64 - 4.5 * 64.0 + self.selected_slot as f64 * 64.0,
-                screen_center.1 as f64 * 2.0 - 64.0,
-                64.0,
-                64.0,
+            for border in &borders {
+                canvas.draw_path(border, None, Some(&INVENTORY_BORDER_STYLE));
+            }
+
+            canvas.draw_path(
+                &rect_path(
+                    (
+                        screen_center.0 as f64 - 4.5 * 64.0 + self.selected_slot as f64 * 64.0,
+                        screen_center.1 as f64 * 2.0 - 64.0,
+                    ),
+                    (64.0, 64.0),
+                ),
+                None,
+                Some(&SELECTED_INVENTORY_BORDER_STYLE),
             );
-            canvas.context.stroke();
+
+            // the breath bar over the hotbar empties while the head is under water
+            if self.breath < MAX_BREATH {
+                let position = (screen_center.0 as f64 - 4.5 * 64.0, screen_center.1 as f64 * 2.0 - 84.0);
+                canvas.fill_rect(position, (64.0 * 9.0, 12.0), &INVENTORY_BACKGROUND_COLOR);
+                let width = 64.0 * 9.0 * self.breath / MAX_BREATH;
+                canvas.fill_rect((position.0 + 2.0, position.1 + 2.0), ((width - 4.0).max(0.0), 8.0), &BREATH_COLOR);
+            }
         }
     }
 }
+
+impl Default for Player {
+    fn default() -> Player {
+        Player::new()
+    }
+}
+
+fn rect_path((x, y): (f64, f64), (w, h): (f64, f64)) -> [(f64, f64); 4] {
+    [(x, y), (x + w, y), (x + w, y + h), (x, y + h)]
+}
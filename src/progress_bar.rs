@@ -40,8 +40,8 @@ impl ProgressBar {
 }
 
 impl Drawable for ProgressBar {
-    fn draw_on_canvas(&self, mut canvas: &mut Canvas) {
-        self.style.apply_on_canvas(&mut canvas);
+    fn draw_on_canvas(&self, canvas: &mut Canvas) {
+        self.style.apply_on_canvas(canvas);
 
         let context = canvas.get_2d_canvas_rendering_context();
 
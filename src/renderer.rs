@@ -0,0 +1,186 @@
+use crate::textures::{Sprite, Textures};
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+use wasm_game_lib::graphics::{canvas::*, color::Color};
+
+/// How new pixels are combined with the pixels already drawn.
+/// These are the subset of canvas `globalCompositeOperation` values used by the game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompositeMode {
+    SourceOver,
+    SourceIn,
+    DestinationOver,
+    /// Multiplies the colors, which darkens and tints what is already drawn.
+    Multiply,
+    /// Inverts, multiplies and inverts again, which brightens what is already drawn.
+    Screen,
+}
+
+impl CompositeMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CompositeMode::SourceOver => "source-over",
+            CompositeMode::SourceIn => "source-in",
+            CompositeMode::DestinationOver => "destination-over",
+            CompositeMode::Multiply => "multiply",
+            CompositeMode::Screen => "screen",
+        }
+    }
+}
+
+/// A surface the game can draw on.
+/// Layers are created by an existing renderer so that they share its textures.
+pub trait Renderer {
+    fn create_layer(&self, width: u32, height: u32) -> Self
+    where
+        Self: Sized;
+    fn get_size(&self) -> (u32, u32);
+    fn clear(&mut self);
+    fn clear_rect(&mut self, position: (f64, f64), size: (f64, f64));
+    fn fill_rect(&mut self, position: (f64, f64), size: (f64, f64), color: &Color);
+    /// Fills the whole surface with a gradient centered on `center`. `stops` are `(offset, color)` pairs between the two radiuses.
+    fn fill_radial_gradient(&mut self, center: (f64, f64), radiuses: (f64, f64), stops: &[(f64, Color)]);
+    /// Fills the whole surface with a gradient going from `start` to `end`.
+    fn fill_linear_gradient(&mut self, start: (f64, f64), end: (f64, f64), stops: &[(f64, Color)]);
+    fn draw_sprite(&mut self, sprite: Sprite, position: (f64, f64));
+    /// Draws the `size` part of an atlas starting at `source` to `position`.
+    fn draw_sprite_part(&mut self, sprite: Sprite, source: (f64, f64), size: (f64, f64), position: (f64, f64));
+    fn draw_layer(&mut self, layer: &Self, position: (f64, f64))
+    where
+        Self: Sized;
+    /// Draws a closed path: stroked first if a style is given, then filled if a color is given.
+    fn draw_path(&mut self, points: &[(f64, f64)], fill: Option<&Color>, stroke: Option<&LineStyle>);
+    fn set_composite_mode(&mut self, mode: CompositeMode);
+}
+
+/// The browser backend, drawing on an html canvas.
+pub struct CanvasRenderer {
+    pub canvas: Canvas,
+    textures: Rc<Textures>,
+}
+
+impl CanvasRenderer {
+    pub fn new(canvas: Canvas, textures: Rc<Textures>) -> CanvasRenderer {
+        CanvasRenderer { canvas, textures }
+    }
+}
+
+impl Renderer for CanvasRenderer {
+    fn create_layer(&self, width: u32, height: u32) -> CanvasRenderer {
+        let mut canvas = Canvas::new();
+        canvas.set_width(width);
+        canvas.set_height(height);
+        CanvasRenderer::new(canvas, Rc::clone(&self.textures))
+    }
+
+    fn get_size(&self) -> (u32, u32) {
+        self.canvas.get_size()
+    }
+
+    fn clear(&mut self) {
+        self.canvas.clear();
+    }
+
+    fn clear_rect(&mut self, position: (f64, f64), size: (f64, f64)) {
+        self.canvas.clear_rect(position, size);
+    }
+
+    fn fill_rect(&mut self, (x, y): (f64, f64), (w, h): (f64, f64), color: &Color) {
+        self.canvas
+            .context
+            .set_fill_style(&JsValue::from_str(&color.to_string()));
+        self.canvas.context.fill_rect(x, y, w, h);
+    }
+
+    fn fill_radial_gradient(&mut self, (x, y): (f64, f64), (r0, r1): (f64, f64), stops: &[(f64, Color)]) {
+        let gradient = self
+            .canvas
+            .context
+            .create_radial_gradient(x, y, r0, x, y, r1)
+            .unwrap();
+        for (offset, color) in stops {
+            gradient
+                .add_color_stop(*offset as f32, &color.to_string())
+                .unwrap();
+        }
+        self.canvas.context.set_fill_style(&gradient);
+        self.canvas.context.fill_rect(
+            0.0,
+            0.0,
+            self.canvas.get_width() as f64,
+            self.canvas.get_height() as f64,
+        );
+    }
+
+    fn fill_linear_gradient(&mut self, (x0, y0): (f64, f64), (x1, y1): (f64, f64), stops: &[(f64, Color)]) {
+        let gradient = self.canvas.context.create_linear_gradient(x0, y0, x1, y1);
+        for (offset, color) in stops {
+            gradient
+                .add_color_stop(*offset as f32, &color.to_string())
+                .unwrap();
+        }
+        self.canvas.context.set_fill_style(&gradient);
+        self.canvas.context.fill_rect(
+            0.0,
+            0.0,
+            self.canvas.get_width() as f64,
+            self.canvas.get_height() as f64,
+        );
+    }
+
+    fn draw_sprite(&mut self, sprite: Sprite, position: (f64, f64)) {
+        self.canvas.draw_image(position, self.textures.get(sprite));
+    }
+
+    fn draw_sprite_part(&mut self, sprite: Sprite, (sx, sy): (f64, f64), (w, h): (f64, f64), (x, y): (f64, f64)) {
+        self.canvas
+            .context
+            .draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                self.textures.get(sprite).get_html_element(),
+                sx,
+                sy,
+                w,
+                h,
+                x,
+                y,
+                w,
+                h,
+            )
+            .unwrap();
+    }
+
+    fn draw_layer(&mut self, layer: &CanvasRenderer, position: (f64, f64)) {
+        self.canvas.draw_canvas(position, &layer.canvas);
+    }
+
+    fn draw_path(&mut self, points: &[(f64, f64)], fill: Option<&Color>, stroke: Option<&LineStyle>) {
+        if points.is_empty() {
+            return;
+        }
+
+        self.canvas.context.begin_path();
+        self.canvas.context.move_to(points[0].0, points[0].1);
+        for (x, y) in &points[1..] {
+            self.canvas.context.line_to(*x, *y);
+        }
+        self.canvas.context.close_path();
+
+        if let Some(style) = stroke {
+            style.apply_on_canvas(&mut self.canvas);
+            self.canvas.context.stroke();
+        }
+        if let Some(color) = fill {
+            self.canvas
+                .context
+                .set_fill_style(&JsValue::from_str(&color.to_string()));
+            self.canvas.context.fill();
+        }
+    }
+
+    fn set_composite_mode(&mut self, mode: CompositeMode) {
+        self.canvas
+            .context
+            .set_global_composite_operation(mode.as_str())
+            .unwrap();
+    }
+}
@@ -0,0 +1,435 @@
+//! Binary save format.
+//!
+//! A save is made of one `world` entry and one `chunk/<number>` entry per chunk.
+//! Every entry starts with `MAGIC` followed by the little endian `SAVE_VERSION`.
+//!
+//! - `world`: seed (u32), first chunk number (i64), chunk count (u32), player x and y (f64),
+//!   inventory slot count (u16) then each slot as presence (u8), item (u16 length and bytes of its name) and quantity (u32),
+//!   then the clock ticks and day length (2 × u64) and the seconds of breath of the player (f64).
+//! - `chunk/<number>`: a palette of the block names used in the chunk (u16 count then u16 length and bytes for each name),
+//!   then for each of the 64 sections whether it is generated (u8) and the blocks of generated sections,
+//!   as a run count (u16) followed by runs of length (u16), block type (u16 palette index),
+//!   natural background (u8), fluid id (u8) and fluid level (u16). Runs go down each of the 32 columns of the section, from left to right.
+//!
+//! Blocks and items are saved by name so that their registries can be reordered without breaking saves.
+//!
+//! Light is not saved since it is computed again when the world is loaded.
+
+use crate::{
+    blocks::{Block, BlockType, NaturalBackground, BLOCKS},
+    chunks::{Chunk, Section, CHUNK_WIDTH, SECTION_COUNT, SECTION_SIZE},
+    fluid::Fluid,
+    items::{Inventory, ITEMS},
+    light::Light,
+};
+use std::collections::HashMap;
+
+pub const MAGIC: &[u8; 4] = b"TRRS";
+pub const SAVE_VERSION: u16 = 1;
+
+#[derive(Debug, PartialEq)]
+pub enum SaveError {
+    Missing,
+    Corrupted,
+    UnsupportedVersion(u16),
+    UnknownBlock(String),
+    UnknownItem(String),
+}
+
+/// Somewhere to persist saves: local storage in the browser, files natively.
+pub trait Storage {
+    fn read(&self, key: &str) -> Option<Vec<u8>>;
+    fn write(&mut self, key: &str, data: &[u8]);
+}
+
+/// A storage keeping everything in memory, mostly useful for tests.
+#[derive(Default)]
+pub struct MemoryStorage {
+    pub entries: HashMap<String, Vec<u8>>,
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> Option<Vec<u8>> {
+        self.entries.get(key).cloned()
+    }
+
+    fn write(&mut self, key: &str, data: &[u8]) {
+        self.entries.insert(key.to_string(), data.to_vec());
+    }
+}
+
+/// Stores each entry in a file of a directory. Slashes in keys are replaced by underscores.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage {
+    directory: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    pub fn new(directory: impl Into<std::path::PathBuf>) -> FileStorage {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory).expect("failed to create the save directory");
+        FileStorage { directory }
+    }
+
+    fn get_path(&self, key: &str) -> std::path::PathBuf {
+        self.directory.join(key.replace('/', "_"))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.get_path(key)).ok()
+    }
+
+    fn write(&mut self, key: &str, data: &[u8]) {
+        std::fs::write(self.get_path(key), data).expect("failed to write a save file");
+    }
+}
+
+/// Stores each entry encoded in base64 in the browser local storage.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage {
+    prefix: String,
+    storage: web_sys::Storage,
+    /// Whether the player was told that the local storage is full.
+    full: bool,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    pub fn new(prefix: &str) -> Option<LocalStorage> {
+        let storage = web_sys::window()?.local_storage().ok()??;
+        Some(LocalStorage {
+            prefix: prefix.to_string(),
+            storage,
+            full: false,
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn read(&self, key: &str) -> Option<Vec<u8>> {
+        let data = self
+            .storage
+            .get_item(&format!("{}/{}", self.prefix, key))
+            .ok()??;
+        decode_base64(&data)
+    }
+
+    fn write(&mut self, key: &str, data: &[u8]) {
+        if self
+            .storage
+            .set_item(&format!("{}/{}", self.prefix, key), &encode_base64(data))
+            .is_err()
+        {
+            wasm_game_lib::elog!("failed to save {}: the local storage is probably full", key);
+            // the save keeps failing until some room is made, the player only needs to be told once
+            if !self.full {
+                self.full = true;
+                if let Some(window) = web_sys::window() {
+                    let _ = window.alert_with_message("The browser storage is full, the world can no longer be saved.");
+                }
+            }
+        }
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes in padded base64, which takes a third more room than the bytes instead of twice as much for hex.
+pub fn encode_base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for group in data.chunks(3) {
+        let bits = group.iter().enumerate().fold(0u32, |bits, (idx, byte)| bits | (*byte as u32) << (16 - 8 * idx));
+        for idx in 0..4 {
+            if idx <= group.len() {
+                encoded.push(BASE64_ALPHABET[(bits >> (18 - 6 * idx)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes padded base64. Returns `None` if it is not valid base64.
+pub fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.as_bytes();
+    if !encoded.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut data = Vec::with_capacity(encoded.len() / 4 * 3);
+    for (group_idx, group) in encoded.chunks(4).enumerate() {
+        let is_last = group_idx == encoded.len() / 4 - 1;
+        let padding = group.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return None;
+        }
+        let mut bits = 0u32;
+        for (idx, c) in group[..4 - padding].iter().enumerate() {
+            let value = BASE64_ALPHABET.iter().position(|letter| letter == c)? as u32;
+            bits |= value << (18 - 6 * idx);
+        }
+        data.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+    }
+    Some(data)
+}
+
+pub fn chunk_key(chunk_number: isize) -> String {
+    format!("chunk/{}", chunk_number)
+}
+
+#[derive(Default)]
+pub struct Writer {
+    pub data: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        let mut writer = Writer { data: Vec::new() };
+        writer.data.extend_from_slice(MAGIC);
+        writer.write_u16(SAVE_VERSION);
+        writer
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_str(&mut self, value: &str) {
+        assert!(value.len() <= u16::MAX as usize, "{} bytes is too long to be saved", value.len());
+        self.write_u16(value.len() as u16);
+        self.data.extend_from_slice(value.as_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_i64(&mut self, value: i64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pub version: u16,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Reader<'a>, SaveError> {
+        if data.len() < 6 || &data[..4] != MAGIC {
+            return Err(SaveError::Corrupted);
+        }
+        let mut reader = Reader {
+            data: &data[4..],
+            version: 0,
+        };
+        reader.version = reader.read_u16()?;
+        if reader.version == 0 || reader.version > SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(reader.version));
+        }
+        Ok(reader)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], SaveError> {
+        if self.data.len() < N {
+            return Err(SaveError::Corrupted);
+        }
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.data[..N]);
+        self.data = &self.data[N..];
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SaveError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, SaveError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, SaveError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_str(&mut self) -> Result<String, SaveError> {
+        let length = self.read_u16()? as usize;
+        if self.data.len() < length {
+            return Err(SaveError::Corrupted);
+        }
+        let value = String::from_utf8(self.data[..length].to_vec()).map_err(|_| SaveError::Corrupted)?;
+        self.data = &self.data[length..];
+        Ok(value)
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, SaveError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_i64(&mut self) -> Result<i64, SaveError> {
+        Ok(i64::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, SaveError> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+}
+
+/// Writes blocks as runs of identical blocks.
+fn write_runs<'a>(writer: &mut Writer, palette: &[BlockType], blocks: impl Iterator<Item = &'a Block>) {
+    let mut runs: Vec<(u16, &Block)> = Vec::new();
+    for block in blocks {
+        match runs.last_mut() {
+            Some((length, previous))
+                if previous.block_type == block.block_type
+                    && previous.natural_background == block.natural_background
+                    && previous.fluid == block.fluid
+                    && previous.fluid_level == block.fluid_level =>
+            {
+                *length += 1
+            }
+            _ => runs.push((1, block)),
+        }
+    }
+
+    writer.write_u16(runs.len() as u16);
+    for (length, block) in runs {
+        writer.write_u16(length);
+        writer.write_u16(palette.iter().position(|block_type| *block_type == block.block_type).unwrap() as u16);
+        writer.write_u8(block.natural_background.get_id());
+        writer.write_u8(block.fluid.get_id());
+        writer.write_u16(block.fluid_level);
+    }
+}
+
+/// Reads `count` blocks written by `write_runs`.
+fn read_runs(reader: &mut Reader, palette: &[BlockType], count: usize) -> Result<Vec<Block>, SaveError> {
+    let mut blocks = Vec::with_capacity(count);
+    for _ in 0..reader.read_u16()? {
+        let length = reader.read_u16()? as usize;
+        let block_type = *palette.get(reader.read_u16()? as usize).ok_or(SaveError::Corrupted)?;
+        let natural_background = NaturalBackground::from_id(reader.read_u8()?).ok_or(SaveError::Corrupted)?;
+        let fluid = Fluid::from_id(reader.read_u8()?).ok_or(SaveError::Corrupted)?;
+        let fluid_level = reader.read_u16()?;
+        if blocks.len() + length > count {
+            return Err(SaveError::Corrupted);
+        }
+        blocks.extend(std::iter::repeat_n(
+            Block {
+                block_type,
+                natural_background,
+                light: Light::BLACK,
+                fluid,
+                fluid_level,
+            },
+            length,
+        ));
+    }
+    if blocks.len() != count {
+        return Err(SaveError::Corrupted);
+    }
+    Ok(blocks)
+}
+
+impl Chunk {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+
+        let mut palette: Vec<BlockType> = Vec::new();
+        for section in 0..SECTION_COUNT {
+            let blocks: &[Block] = match self.section(section) {
+                Section::Ungenerated => &[],
+                Section::Uniform(block) => std::slice::from_ref(block),
+                Section::Full(blocks) => blocks,
+            };
+            for block in blocks {
+                if !palette.contains(&block.block_type) {
+                    palette.push(block.block_type);
+                }
+            }
+        }
+        writer.write_u16(palette.len() as u16);
+        for block_type in &palette {
+            writer.write_str(block_type.get_name());
+        }
+
+        for section in 0..SECTION_COUNT {
+            match self.section(section) {
+                Section::Ungenerated => writer.write_u8(0),
+                Section::Uniform(block) => {
+                    writer.write_u8(1);
+                    write_runs(&mut writer, &palette, std::iter::repeat_n(block, CHUNK_WIDTH * SECTION_SIZE));
+                }
+                Section::Full(blocks) => {
+                    writer.write_u8(1);
+                    write_runs(&mut writer, &palette, blocks.iter());
+                }
+            }
+        }
+
+        writer.data
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Chunk, SaveError> {
+        let mut reader = Reader::new(data)?;
+        let palette = (0..reader.read_u16()?)
+            .map(|_| {
+                let name = reader.read_str()?;
+                BLOCKS.get_by_name(&name).ok_or(SaveError::UnknownBlock(name))
+            })
+            .collect::<Result<Vec<BlockType>, SaveError>>()?;
+
+        let mut chunk = Chunk::new();
+        for section in 0..SECTION_COUNT {
+            if reader.read_u8()? == 1 {
+                let blocks = read_runs(&mut reader, &palette, CHUNK_WIDTH * SECTION_SIZE)?;
+                chunk.set_section(section, Section::from_blocks(blocks));
+            }
+        }
+
+        Ok(chunk)
+    }
+}
+
+pub fn write_inventory(writer: &mut Writer, inventory: &Inventory) {
+    writer.write_u16(inventory.len() as u16);
+    for idx in 0..inventory.len() {
+        match inventory[idx] {
+            Some((item, quantity)) => {
+                writer.write_u8(1);
+                writer.write_str(item.get_name());
+                writer.write_u32(quantity as u32);
+            }
+            None => writer.write_u8(0),
+        }
+    }
+}
+
+pub fn read_inventory(reader: &mut Reader) -> Result<Inventory, SaveError> {
+    let mut inventory = Inventory::new(reader.read_u16()? as usize);
+    for idx in 0..inventory.len() {
+        if reader.read_u8()? == 1 {
+            let name = reader.read_str()?;
+            let item = ITEMS.get_by_name(&name).ok_or(SaveError::UnknownItem(name))?;
+            inventory[idx] = Some((item, reader.read_u32()? as usize));
+        }
+    }
+    Ok(inventory)
+}
@@ -0,0 +1,44 @@
+use std::hash::Hasher;
+use twox_hash::XxHash32;
+
+/// The seed every random choice of the world generation is derived from.
+/// Two worlds with the same seed are generated identically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorldSeed(pub u32);
+
+impl WorldSeed {
+    #[cfg(target_arch = "wasm32")]
+    pub fn random() -> WorldSeed {
+        WorldSeed((js_sys::Math::random() * u32::MAX as f64) as u32)
+    }
+
+    /// Returns a hasher seeded with this seed. Write the coordinates of what is generated into it.
+    pub fn hasher(self) -> XxHash32 {
+        XxHash32::with_seed(self.0)
+    }
+
+    /// Parses a seed typed by a player. Numbers are used as is and any other text is hashed.
+    pub fn parse(text: &str) -> WorldSeed {
+        let text = text.trim();
+        match text.parse::<u32>() {
+            Ok(seed) => WorldSeed(seed),
+            Err(_) => {
+                let mut hasher = XxHash32::with_seed(0);
+                hasher.write(text.as_bytes());
+                WorldSeed(hasher.finish() as u32)
+            }
+        }
+    }
+}
+
+impl Default for WorldSeed {
+    fn default() -> WorldSeed {
+        WorldSeed(42)
+    }
+}
+
+impl std::fmt::Display for WorldSeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -0,0 +1,477 @@
+use crate::{
+    renderer::{CompositeMode, Renderer},
+    textures::{sprites, Sprite},
+};
+use std::rc::Rc;
+use wasm_game_lib::graphics::{canvas::LineStyle, color::Color};
+
+const TILE_SIZE: usize = 64;
+
+/// An image stored as non-premultiplied RGBA bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let idx = (y as usize * self.width as usize + x as usize) * 4;
+        [
+            self.pixels[idx],
+            self.pixels[idx + 1],
+            self.pixels[idx + 2],
+            self.pixels[idx + 3],
+        ]
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn decode_png(data: &[u8]) -> Option<RgbaImage> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().ok()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).ok()?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks(3)
+                .flat_map(|p| vec![p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks(2)
+                .flat_map(|p| vec![p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer
+                .iter()
+                .flat_map(|p| vec![*p, *p, *p, 255])
+                .collect(),
+            png::ColorType::Indexed => return None,
+        };
+
+        Some(RgbaImage {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn encode_png(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&self.pixels).unwrap();
+        }
+        data
+    }
+}
+
+/// The textures of every sprite, decoded in memory.
+pub struct SoftwareTextures {
+    images: Vec<RgbaImage>,
+}
+
+impl SoftwareTextures {
+    pub fn new(images: Vec<RgbaImage>) -> SoftwareTextures {
+        assert_eq!(images.len(), sprites().len());
+        SoftwareTextures { images }
+    }
+
+    /// Loads the textures from the directory containing the `ressources` folder.
+    /// Missing or unreadable files are replaced by an empty image.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(root: &std::path::Path) -> SoftwareTextures {
+        let images = sprites()
+            .into_iter()
+            .map(|sprite| {
+                std::fs::read(root.join(sprite.get_path()))
+                    .ok()
+                    .and_then(|data| RgbaImage::decode_png(&data))
+                    .unwrap_or(RgbaImage {
+                        width: 0,
+                        height: 0,
+                        pixels: Vec::new(),
+                    })
+            })
+            .collect();
+
+        SoftwareTextures::new(images)
+    }
+
+    pub fn get(&self, sprite: Sprite) -> &RgbaImage {
+        &self.images[sprite.index()]
+    }
+}
+
+/// A CPU backend rasterizing into an RGBA buffer.
+/// Pixels are stored in tiles allocated on the first write so that huge layers stay cheap.
+pub struct SoftwareRenderer {
+    width: u32,
+    height: u32,
+    tiles: Vec<Option<Box<[u8]>>>,
+    composite_mode: CompositeMode,
+    textures: Rc<SoftwareTextures>,
+}
+
+impl SoftwareRenderer {
+    pub fn new(width: u32, height: u32, textures: Rc<SoftwareTextures>) -> SoftwareRenderer {
+        let tiles_x = (width as usize).div_ceil(TILE_SIZE);
+        let tiles_y = (height as usize).div_ceil(TILE_SIZE);
+        SoftwareRenderer {
+            width,
+            height,
+            tiles: vec![None; tiles_x * tiles_y],
+            composite_mode: CompositeMode::SourceOver,
+            textures,
+        }
+    }
+
+    fn tile_index(&self, x: usize, y: usize) -> (usize, usize) {
+        let tiles_x = (self.width as usize).div_ceil(TILE_SIZE);
+        (
+            (y / TILE_SIZE) * tiles_x + x / TILE_SIZE,
+            ((y % TILE_SIZE) * TILE_SIZE + x % TILE_SIZE) * 4,
+        )
+    }
+
+    pub fn get_pixel(&self, x: i64, y: i64) -> [u8; 4] {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return [0; 4];
+        }
+        let (tile, idx) = self.tile_index(x as usize, y as usize);
+        match &self.tiles[tile] {
+            Some(tile) => [tile[idx], tile[idx + 1], tile[idx + 2], tile[idx + 3]],
+            None => [0; 4],
+        }
+    }
+
+    fn set_pixel(&mut self, x: i64, y: i64, color: [u8; 4]) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let (tile, idx) = self.tile_index(x as usize, y as usize);
+        if self.tiles[tile].is_none() {
+            if color[3] == 0 {
+                return;
+            }
+            self.tiles[tile] = Some(vec![0; TILE_SIZE * TILE_SIZE * 4].into_boxed_slice());
+        }
+        if let Some(tile) = &mut self.tiles[tile] {
+            tile[idx..idx + 4].copy_from_slice(&color);
+        }
+    }
+
+    fn blend_pixel(&mut self, x: i64, y: i64, source: [u8; 4]) {
+        let destination = self.get_pixel(x, y);
+        let result = blend(source, destination, self.composite_mode);
+        self.set_pixel(x, y, result);
+    }
+
+    /// With `source-in`, a canvas also clears everything outside of the drawn area.
+    fn clear_outside(&mut self, (x0, y0): (i64, i64), (x1, y1): (i64, i64)) {
+        if self.composite_mode != CompositeMode::SourceIn {
+            return;
+        }
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                if x < x0 || x >= x1 || y < y0 || y >= y1 {
+                    self.set_pixel(x, y, [0; 4]);
+                }
+            }
+        }
+    }
+
+    fn fill_pixels_where(&mut self, bounds: ((i64, i64), (i64, i64)), color: [u8; 4], inside: impl Fn(f64, f64) -> bool) {
+        let ((x0, y0), (x1, y1)) = bounds;
+        for y in y0.max(0)..y1.min(self.height as i64) {
+            for x in x0.max(0)..x1.min(self.width as i64) {
+                if inside(x as f64 + 0.5, y as f64 + 0.5) {
+                    self.blend_pixel(x, y, color);
+                } else if self.composite_mode == CompositeMode::SourceIn {
+                    self.set_pixel(x, y, [0; 4]);
+                }
+            }
+        }
+        self.clear_outside((x0, y0), (x1, y1));
+    }
+
+    pub fn to_image(&self) -> RgbaImage {
+        let mut pixels = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                pixels.extend_from_slice(&self.get_pixel(x, y));
+            }
+        }
+        RgbaImage {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+}
+
+fn color_to_array(color: &Color) -> [u8; 4] {
+    [color.red, color.green, color.blue, color.alpha]
+}
+
+/// Color of a gradient at `t`, between 0 and 1.
+fn gradient_color(stops: &[(f64, Color)], t: f64) -> [u8; 4] {
+    if t <= stops[0].0 {
+        return color_to_array(&stops[0].1);
+    }
+    for pair in stops.windows(2) {
+        let ((t0, c0), (t1, c1)) = (&pair[0], &pair[1]);
+        if t >= *t0 && t <= *t1 {
+            let f = if t1 > t0 { (t - t0) / (t1 - t0) } else { 0.0 };
+            let (c0, c1) = (color_to_array(c0), color_to_array(c1));
+            let mut color = [0; 4];
+            for i in 0..4 {
+                color[i] = (c0[i] as f64 + (c1[i] as f64 - c0[i] as f64) * f).round() as u8;
+            }
+            return color;
+        }
+    }
+    color_to_array(&stops[stops.len() - 1].1)
+}
+
+fn blend(source: [u8; 4], destination: [u8; 4], mode: CompositeMode) -> [u8; 4] {
+    let sa = source[3] as f64 / 255.0;
+    let da = destination[3] as f64 / 255.0;
+
+    let alpha = match mode {
+        CompositeMode::SourceOver | CompositeMode::Multiply | CompositeMode::Screen => sa + da * (1.0 - sa),
+        CompositeMode::SourceIn => sa * da,
+        CompositeMode::DestinationOver => da + sa * (1.0 - da),
+    };
+    if alpha <= 0.0 {
+        return [0; 4];
+    }
+
+    let mut result = [0; 4];
+    for i in 0..3 {
+        let (s, d) = (source[i] as f64, destination[i] as f64);
+        let value = match mode {
+            CompositeMode::SourceOver => (s * sa + d * da * (1.0 - sa)) / alpha,
+            CompositeMode::SourceIn => s,
+            CompositeMode::DestinationOver => (d * da + s * sa * (1.0 - da)) / alpha,
+            CompositeMode::Multiply | CompositeMode::Screen => {
+                // separable blend modes mix both colors where they overlap and behave like source-over elsewhere
+                let mixed = match mode {
+                    CompositeMode::Multiply => s * d / 255.0,
+                    _ => s + d - s * d / 255.0,
+                };
+                (s * sa * (1.0 - da) + mixed * sa * da + d * da * (1.0 - sa)) / alpha
+            }
+        };
+        result[i] = value.round() as u8;
+    }
+    result[3] = (alpha * 255.0).round() as u8;
+    result
+}
+
+fn distance_to_segment((px, py): (f64, f64), (ax, ay): (f64, f64), (bx, by): (f64, f64)) -> f64 {
+    let (dx, dy) = (bx - ax, by - ay);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((px - ax) * dx + (py - ay) * dy) / length).clamp(0.0, 1.0)
+    };
+    let (cx, cy) = (ax + t * dx, ay + t * dy);
+    ((px - cx) * (px - cx) + (py - cy) * (py - cy)).sqrt()
+}
+
+/// Nonzero winding rule, like the default canvas fill rule.
+fn is_inside_polygon(points: &[(f64, f64)], (px, py): (f64, f64)) -> bool {
+    let mut winding = 0;
+    for i in 0..points.len() {
+        let (ax, ay) = points[i];
+        let (bx, by) = points[(i + 1) % points.len()];
+        let side = (bx - ax) * (py - ay) - (px - ax) * (by - ay);
+        if ay <= py {
+            if by > py && side > 0.0 {
+                winding += 1;
+            }
+        } else if by <= py && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding != 0
+}
+
+fn bounds_of(points: &[(f64, f64)], margin: f64) -> ((i64, i64), (i64, i64)) {
+    let mut min = (f64::MAX, f64::MAX);
+    let mut max = (f64::MIN, f64::MIN);
+    for (x, y) in points {
+        min = (min.0.min(*x), min.1.min(*y));
+        max = (max.0.max(*x), max.1.max(*y));
+    }
+    (
+        ((min.0 - margin).floor() as i64, (min.1 - margin).floor() as i64),
+        ((max.0 + margin).ceil() as i64 + 1, (max.1 + margin).ceil() as i64 + 1),
+    )
+}
+
+impl Renderer for SoftwareRenderer {
+    fn create_layer(&self, width: u32, height: u32) -> SoftwareRenderer {
+        SoftwareRenderer::new(width, height, Rc::clone(&self.textures))
+    }
+
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn clear(&mut self) {
+        for tile in self.tiles.iter_mut() {
+            *tile = None;
+        }
+    }
+
+    fn clear_rect(&mut self, (x, y): (f64, f64), (w, h): (f64, f64)) {
+        for py in (y.round() as i64).max(0)..((y + h).round() as i64).min(self.height as i64) {
+            for px in (x.round() as i64).max(0)..((x + w).round() as i64).min(self.width as i64) {
+                self.set_pixel(px, py, [0; 4]);
+            }
+        }
+    }
+
+    fn fill_rect(&mut self, (x, y): (f64, f64), (w, h): (f64, f64), color: &Color) {
+        let bounds = (
+            (x.floor() as i64, y.floor() as i64),
+            ((x + w).ceil() as i64, (y + h).ceil() as i64),
+        );
+        self.fill_pixels_where(bounds, color_to_array(color), |px, py| {
+            px >= x && px < x + w && py >= y && py < y + h
+        });
+    }
+
+    fn fill_radial_gradient(&mut self, (cx, cy): (f64, f64), (r0, r1): (f64, f64), stops: &[(f64, Color)]) {
+        if stops.is_empty() {
+            return;
+        }
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                let distance = ((x as f64 + 0.5 - cx).powi(2) + (y as f64 + 0.5 - cy).powi(2)).sqrt();
+                let t = ((distance - r0) / (r1 - r0)).clamp(0.0, 1.0);
+                self.blend_pixel(x, y, gradient_color(stops, t));
+            }
+        }
+    }
+
+    fn fill_linear_gradient(&mut self, (x0, y0): (f64, f64), (x1, y1): (f64, f64), stops: &[(f64, Color)]) {
+        let length = (x1 - x0).powi(2) + (y1 - y0).powi(2);
+        if stops.is_empty() || length == 0.0 {
+            return;
+        }
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                // projection of the pixel on the gradient line
+                let t = (((x as f64 + 0.5 - x0) * (x1 - x0) + (y as f64 + 0.5 - y0) * (y1 - y0)) / length).clamp(0.0, 1.0);
+                self.blend_pixel(x, y, gradient_color(stops, t));
+            }
+        }
+    }
+
+    fn draw_sprite(&mut self, sprite: Sprite, position: (f64, f64)) {
+        let (width, height) = {
+            let image = self.textures.get(sprite);
+            (image.width as f64, image.height as f64)
+        };
+        self.draw_sprite_part(sprite, (0.0, 0.0), (width, height), position);
+    }
+
+    fn draw_sprite_part(&mut self, sprite: Sprite, (sx, sy): (f64, f64), (w, h): (f64, f64), (x, y): (f64, f64)) {
+        let textures = Rc::clone(&self.textures);
+        let image = textures.get(sprite);
+        let (x0, y0) = (x.round() as i64, y.round() as i64);
+        let (sx, sy) = (sx.round() as i64, sy.round() as i64);
+
+        for dy in 0..h.round() as i64 {
+            for dx in 0..w.round() as i64 {
+                let (ix, iy) = (sx + dx, sy + dy);
+                let color = if ix < 0 || iy < 0 || ix >= image.width as i64 || iy >= image.height as i64 {
+                    [0; 4]
+                } else {
+                    image.get_pixel(ix as u32, iy as u32)
+                };
+                self.blend_pixel(x0 + dx, y0 + dy, color);
+            }
+        }
+        self.clear_outside((x0, y0), (x0 + w.round() as i64, y0 + h.round() as i64));
+    }
+
+    fn draw_layer(&mut self, layer: &SoftwareRenderer, (x, y): (f64, f64)) {
+        let (x0, y0) = (x.round() as i64, y.round() as i64);
+        let (x1, y1) = (x0 + layer.width as i64, y0 + layer.height as i64);
+
+        if self.composite_mode == CompositeMode::SourceIn {
+            for py in y0.max(0)..y1.min(self.height as i64) {
+                for px in x0.max(0)..x1.min(self.width as i64) {
+                    let color = layer.get_pixel(px - x0, py - y0);
+                    self.blend_pixel(px, py, color);
+                }
+            }
+            self.clear_outside((x0, y0), (x1, y1));
+            return;
+        }
+
+        // transparent pixels don't change anything here, so only allocated tiles are visited
+        let tiles_x = (layer.width as usize).div_ceil(TILE_SIZE);
+        for (tile_index, tile) in layer.tiles.iter().enumerate() {
+            let tile = match tile {
+                Some(tile) => tile,
+                None => continue,
+            };
+            let tile_x = ((tile_index % tiles_x) * TILE_SIZE) as i64;
+            let tile_y = ((tile_index / tiles_x) * TILE_SIZE) as i64;
+            if x0 + tile_x >= self.width as i64
+                || y0 + tile_y >= self.height as i64
+                || x0 + tile_x + (TILE_SIZE as i64) < 0
+                || y0 + tile_y + (TILE_SIZE as i64) < 0
+            {
+                continue;
+            }
+
+            for ty in 0..TILE_SIZE as i64 {
+                for tx in 0..TILE_SIZE as i64 {
+                    let idx = (ty as usize * TILE_SIZE + tx as usize) * 4;
+                    let color = [tile[idx], tile[idx + 1], tile[idx + 2], tile[idx + 3]];
+                    if color[3] != 0 {
+                        self.blend_pixel(x0 + tile_x + tx, y0 + tile_y + ty, color);
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_path(&mut self, points: &[(f64, f64)], fill: Option<&Color>, stroke: Option<&LineStyle>) {
+        if points.is_empty() {
+            return;
+        }
+
+        if let Some(style) = stroke {
+            let half_width = style.size / 2.0;
+            self.fill_pixels_where(bounds_of(points, half_width), color_to_array(&style.color), |px, py| {
+                (0..points.len()).any(|i| {
+                    distance_to_segment((px, py), points[i], points[(i + 1) % points.len()]) <= half_width
+                })
+            });
+        }
+        if let Some(color) = fill {
+            self.fill_pixels_where(bounds_of(points, 0.0), color_to_array(color), |px, py| {
+                is_inside_polygon(points, (px, py))
+            });
+        }
+    }
+
+    fn set_composite_mode(&mut self, mode: CompositeMode) {
+        self.composite_mode = mode;
+    }
+}
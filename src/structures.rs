@@ -0,0 +1,298 @@
+//! Structures stamped over the terrain: cabins, underground ruins and treasure rooms.
+//!
+//! The prefabs are loaded from `ressources/structures.json`. Each one is a grid of characters and a palette giving the block of each character,
+//! spaces keeping the generated terrain. The world is cut into regions of `REGION_WIDTH` columns holding at most one structure,
+//! and a structure only depends on the seed and on its region, so that every section it crosses stamps its own part of it
+//! whatever the order in which they are generated.
+
+use crate::{
+    biomes::{biome_at, Biome},
+    blocks::{BlockType, BLOCKS},
+    chunks::surface_height,
+    items::Item,
+    seed::WorldSeed,
+    water::{is_near_water, SHORE_WIDTH},
+    world::World,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, hash::Hasher, sync::LazyLock};
+
+/// Columns of each region. A structure is entirely contained in its region so that structures never overlap.
+pub const REGION_WIDTH: isize = 256;
+/// Prefabs can't be wider than this, which leaves room to choose where they stand in their region.
+pub const MAX_PREFAB_WIDTH: usize = 64;
+/// One region out of this many has no structure, and an aquifer instead.
+const EMPTY_REGIONS: u64 = 3;
+const REGION_HASH_LAYER: u8 = 16;
+
+/// The prefabs of every structure, loaded from `ressources/structures.json`.
+pub static PREFABS: LazyLock<PrefabRegistry> = LazyLock::new(|| {
+    PrefabRegistry::from_json(include_str!("../pkg/ressources/structures.json")).unwrap()
+});
+
+/// Items which may be found in a chest.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LootEntry {
+    pub item: Item,
+    /// Minimum and maximum quantity, both included.
+    pub count: (usize, usize),
+    /// Probability for the item to be in a chest.
+    pub chance: f64,
+}
+
+/// A prefab as written in the json file.
+#[derive(Debug, Clone, Deserialize)]
+struct PrefabDefinition {
+    name: String,
+    weight: u32,
+    /// Biomes where the structure can be placed, all of them if missing.
+    #[serde(default)]
+    biomes: Option<Vec<Biome>>,
+    depth: (isize, isize),
+    ground: usize,
+    palette: HashMap<char, String>,
+    rows: Vec<String>,
+    #[serde(default)]
+    loot: Vec<LootEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Prefab {
+    pub name: String,
+    /// How often the prefab is chosen compared to the other ones.
+    pub weight: u32,
+    pub biomes: Option<Vec<Biome>>,
+    /// Minimum and maximum number of blocks between the surface and the ground row, both included.
+    pub depth: (isize, isize),
+    /// The row of the prefab placed at the chosen depth. It is the floor of the structure.
+    pub ground: usize,
+    pub width: usize,
+    pub height: usize,
+    /// Blocks of the prefab row by row, `None` where the terrain is kept.
+    cells: Vec<Option<BlockType>>,
+    /// Loot of the chests of the structure.
+    pub loot: Vec<LootEntry>,
+}
+
+impl Prefab {
+    fn from_definition(definition: PrefabDefinition) -> Result<Prefab, String> {
+        let name = definition.name;
+        let width = definition.rows.first().map_or(0, |row| row.chars().count());
+        if width == 0 || width > MAX_PREFAB_WIDTH {
+            return Err(format!("{} must be between 1 and {} blocks wide", name, MAX_PREFAB_WIDTH));
+        }
+        if definition.ground >= definition.rows.len() {
+            return Err(format!("the ground of {} is outside of its rows", name));
+        }
+        if definition.depth.0 > definition.depth.1 || definition.loot.iter().any(|entry| entry.count.0 > entry.count.1) {
+            return Err(format!("{} has an empty range", name));
+        }
+
+        let mut palette = HashMap::new();
+        for (character, block_name) in &definition.palette {
+            match BLOCKS.get_by_name(block_name) {
+                Some(block_type) => palette.insert(*character, block_type),
+                None => return Err(format!("{} uses the unknown block {}", name, block_name)),
+            };
+        }
+        let mut cells = Vec::with_capacity(width * definition.rows.len());
+        for row in &definition.rows {
+            if row.chars().count() != width {
+                return Err(format!("the rows of {} don't have the same width", name));
+            }
+            for character in row.chars() {
+                match (character, palette.get(&character)) {
+                    (' ', _) => cells.push(None),
+                    (_, Some(block_type)) => cells.push(Some(*block_type)),
+                    (_, None) => return Err(format!("{} is not in the palette of {}", character, name)),
+                }
+            }
+        }
+
+        Ok(Prefab {
+            name,
+            weight: definition.weight,
+            biomes: definition.biomes,
+            depth: definition.depth,
+            ground: definition.ground,
+            width,
+            height: definition.rows.len(),
+            cells,
+            loot: definition.loot,
+        })
+    }
+
+    /// The block of the prefab at a position relative to its top left corner, `None` where the terrain is kept.
+    pub fn get(&self, x: usize, y: usize) -> Option<BlockType> {
+        match x < self.width && y < self.height {
+            true => self.cells[y * self.width + x],
+            false => None,
+        }
+    }
+
+    pub fn can_be_placed_in(&self, biome: Biome) -> bool {
+        self.biomes.as_ref().is_none_or(|biomes| biomes.contains(&biome))
+    }
+
+    /// Rolls the content of a chest. The same seed and position always give the same items.
+    pub fn roll_loot(&self, seed: WorldSeed, (x, y): (isize, isize)) -> Vec<Item> {
+        let mut items = Vec::new();
+        for (idx, entry) in self.loot.iter().enumerate() {
+            let mut hasher = seed.hasher();
+            hasher.write_i64(x as i64);
+            hasher.write_i64(y as i64);
+            hasher.write_u64(idx as u64);
+            let hash = hasher.finish();
+            if (hash % 10000) as f64 / 10000.0 >= entry.chance {
+                continue;
+            }
+            let count = entry.count.0 + (hash >> 16) as usize % (entry.count.1 - entry.count.0 + 1);
+            items.extend(std::iter::repeat_n(entry.item, count));
+        }
+        items
+    }
+}
+
+pub struct PrefabRegistry {
+    prefabs: Vec<Prefab>,
+}
+
+impl PrefabRegistry {
+    pub fn from_json(json: &str) -> Result<PrefabRegistry, String> {
+        let definitions: Vec<PrefabDefinition> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let prefabs = definitions
+            .into_iter()
+            .map(Prefab::from_definition)
+            .collect::<Result<Vec<Prefab>, String>>()?;
+        Ok(PrefabRegistry { prefabs })
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<usize> {
+        self.prefabs.iter().position(|prefab| prefab.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.prefabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefabs.is_empty()
+    }
+}
+
+impl std::ops::Index<usize> for PrefabRegistry {
+    type Output = Prefab;
+
+    fn index(&self, idx: usize) -> &Prefab {
+        &self.prefabs[idx]
+    }
+}
+
+/// Hashes of the regions use layers after the ones of the tree columns so that they don't follow them.
+fn region_hash(seed: WorldSeed, region: isize, layer: u8) -> u64 {
+    let mut hasher = seed.hasher();
+    hasher.write_i64(region as i64);
+    hasher.write_u8(REGION_HASH_LAYER + layer);
+    hasher.finish()
+}
+
+pub fn is_empty_region(seed: WorldSeed, region: isize) -> bool {
+    region_hash(seed, region, 0).is_multiple_of(EMPTY_REGIONS)
+}
+
+/// A prefab placed in the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Structure {
+    /// Index of the prefab in `PREFABS`.
+    pub prefab: usize,
+    /// Top left corner of the structure.
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Structure {
+    /// The structure of a region, if it has one.
+    pub fn in_region(seed: WorldSeed, region: isize) -> Option<Structure> {
+        if is_empty_region(seed, region) {
+            return None;
+        }
+        let hash = region_hash(seed, region, 0);
+        let x = region * REGION_WIDTH + (hash >> 8) as isize % (REGION_WIDTH - MAX_PREFAB_WIDTH as isize);
+
+        // the prefab is chosen among the ones which fit in the biome of the left edge of the structure
+        let biome = biome_at(seed, x);
+        let candidates: Vec<usize> = (0..PREFABS.len()).filter(|idx| PREFABS[*idx].can_be_placed_in(biome)).collect();
+        let total_weight: u64 = candidates.iter().map(|idx| PREFABS[*idx].weight as u64).sum();
+        if total_weight == 0 {
+            return None;
+        }
+        let mut choice = region_hash(seed, region, 1) % total_weight;
+        let mut prefab = candidates[0];
+        for idx in candidates {
+            prefab = idx;
+            match choice.checked_sub(PREFABS[idx].weight as u64) {
+                Some(remaining) => choice = remaining,
+                None => break,
+            }
+        }
+
+        // structures are kept away from lakes and rivers so that they are never flooded
+        let prefab_ref = &PREFABS[prefab];
+        if is_near_water(seed, x - SHORE_WIDTH, x + prefab_ref.width as isize + SHORE_WIDTH) {
+            return None;
+        }
+        // the ground row goes down to the lowest column of the terrain so that the structure never floats
+        let surface = (x..x + prefab_ref.width as isize)
+            .map(|x| surface_height(seed, x).floor().clamp(1.0, 2047.0) as isize)
+            .max()
+            .unwrap();
+        let (min_depth, max_depth) = prefab_ref.depth;
+        let depth = min_depth + (region_hash(seed, region, 2) % (max_depth - min_depth + 1) as u64) as isize;
+        Some(Structure {
+            prefab,
+            x,
+            y: surface + depth - prefab_ref.ground as isize,
+        })
+    }
+
+    pub fn get_prefab(&self) -> &'static Prefab {
+        &PREFABS[self.prefab]
+    }
+
+    /// The block stamped by the structure at a position, `None` where the terrain is kept.
+    pub fn get(&self, (x, y): (isize, isize)) -> Option<BlockType> {
+        if x < self.x || y < self.y {
+            return None;
+        }
+        self.get_prefab().get((x - self.x) as usize, (y - self.y) as usize)
+    }
+
+    /// Rows covered by the structure.
+    pub fn rows(&self) -> std::ops::Range<isize> {
+        self.y..self.y + self.get_prefab().height as isize
+    }
+}
+
+/// The structures which may stamp blocks in the columns from `start` to `end`, `end` excluded.
+pub fn structures_between(seed: WorldSeed, start: isize, end: isize) -> Vec<Structure> {
+    (start.div_euclid(REGION_WIDTH)..=(end - 1).div_euclid(REGION_WIDTH))
+        .filter_map(|region| Structure::in_region(seed, region))
+        .collect()
+}
+
+/// The structure stamping a block at a position, if there is one.
+pub fn structure_at(seed: WorldSeed, (x, y): (isize, isize)) -> Option<Structure> {
+    Structure::in_region(seed, x.div_euclid(REGION_WIDTH)).filter(|structure| structure.get((x, y)).is_some())
+}
+
+impl World {
+    /// Returns the items of a chest placed by a structure, which are rolled from the loot table of the structure
+    /// and the position of the chest. Chests can't be placed by players so there is nothing to store until they are broken.
+    pub fn open_chest(&self, position: (isize, isize)) -> Vec<Item> {
+        let chest = BlockType::from_name("chest");
+        match structure_at(self.seed, position) {
+            Some(structure) if structure.get(position) == Some(chest) => structure.get_prefab().roll_loot(self.seed, position),
+            _ => Vec::new(),
+        }
+    }
+}
@@ -1,58 +1,82 @@
-use crate::{items::Item, loader::load_images};
+use crate::{
+    items::{Item, ITEMS},
+    loader::load_images,
+};
+use serde::Deserialize;
 use wasm_game_lib::graphics::{canvas::Canvas, image::Image};
 
 // resize : convert running.png -interpolate Integer -filter point -resize "200%" output.png
 
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sprite {
+    CharacterIdle,
+    CharacterIdleLeft,
+    CharacterRunning,
+    CharacterRunningLeft,
+    Blocks,
+    BackgroundDirt,
+    Torch,
+    /// The icon of an item, whose path comes from the item registry.
+    Item(Item),
+}
+
+/// The sprites which are not item icons, in the order of their textures.
+const FIXED_SPRITES: [Sprite; 7] = [
+    Sprite::CharacterIdle,
+    Sprite::CharacterIdleLeft,
+    Sprite::CharacterRunning,
+    Sprite::CharacterRunningLeft,
+    Sprite::Blocks,
+    Sprite::BackgroundDirt,
+    Sprite::Torch,
+];
+
+/// Every sprite in the order of their textures, the fixed ones and then the icons of the items.
+pub fn sprites() -> Vec<Sprite> {
+    FIXED_SPRITES.iter().copied().chain(ITEMS.iter().map(Sprite::Item)).collect()
+}
+
+impl Sprite {
+    /// Position of the texture of the sprite in `sprites()`.
+    pub fn index(self) -> usize {
+        match self {
+            Sprite::Item(item) => FIXED_SPRITES.len() + item.0 as usize,
+            sprite => FIXED_SPRITES.iter().position(|fixed| *fixed == sprite).unwrap(),
+        }
+    }
+
+    pub fn get_path(self) -> &'static str {
+        match self {
+            Sprite::CharacterIdle => "ressources/character/idle.png",
+            Sprite::CharacterIdleLeft => "ressources/character/idle2.png",
+            Sprite::CharacterRunning => "ressources/character/running.png",
+            Sprite::CharacterRunningLeft => "ressources/character/running2.png",
+            Sprite::Blocks => "ressources/blocks/atlas.png",
+            Sprite::BackgroundDirt => "ressources/backgrounds/dirt.png",
+            Sprite::Torch => "ressources/blocks/torch.png",
+            Sprite::Item(item) => &item.get_properties().icon,
+        }
+    }
+}
+
 pub struct Textures {
-    pub character: ((Image, Image), (Image, Image)),
-    pub grass: Image,
-    pub dirt: Image,
-    pub tree: Image,
-    pub item_log: Image,
-    pub item_wood_stick: Image,
-    pub item_foliage: Image,
-    pub background_dirt: Image,
+    images: Vec<Image>,
 }
 
 impl Textures {
-    pub async fn load(mut canvas: &mut Canvas) -> Textures {
-        let mut t = load_images(
-            vec![
-                "ressources/character/idle.png",
-                "ressources/character/idle2.png",
-                "ressources/character/running.png",
-                "ressources/character/running2.png",
-                "ressources/blocks/grass.png",
-                "ressources/blocks/dirt.png",
-                "ressources/backgrounds/dirt.png",
-                "ressources/tree.png",
-                "ressources/items/log.png",
-                "ressources/items/wood_stick.png",
-                "ressources/items/foliage.png",
-            ],
-            &mut canvas,
+    pub async fn load(canvas: &mut Canvas) -> Textures {
+        let images = load_images(
+            sprites().into_iter().map(|sprite| sprite.get_path()).collect(),
+            canvas,
         )
         .await;
 
-        Textures {
-            character: ((t.remove(0), t.remove(0)), (t.remove(0), t.remove(0))),
-            grass: t.remove(0),
-            dirt: t.remove(0),
-            background_dirt: t.remove(0),
-            tree: t.remove(0),
-            item_log: t.remove(0),
-            item_wood_stick: t.remove(0),
-            item_foliage: t.remove(0),
-        }
+        Textures { images }
     }
 
-    pub fn get_for_item(&self, item: Item) -> &Image {
-        match item {
-            Item::Dirt => &self.dirt,
-            Item::Log => &self.item_log,
-            Item::WoodStick => &self.item_wood_stick,
-            Item::Foliage => &self.item_foliage,
-        }
+    pub fn get(&self, sprite: Sprite) -> &Image {
+        &self.images[sprite.index()]
     }
 }
 
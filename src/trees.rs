@@ -0,0 +1,183 @@
+//! Trees made of log and leaves blocks.
+//!
+//! Whether a column has a tree and the shape of the tree only depend on the seed and on the column,
+//! so that a section can place the parts of trees rooted in its neighbours without generating them.
+
+use crate::{
+    biomes::biome_at, blocks::BlockType, chunks::surface_height, items::Item, seed::WorldSeed, structures::structure_at, water::is_shore,
+    world::World,
+};
+use std::hash::Hasher;
+
+/// Trees are at least this many columns apart so that their leaves never touch.
+pub const MIN_TREE_DISTANCE: isize = 6;
+/// How far the branches and the leaves of a tree go from its trunk.
+pub const TREE_RADIUS: isize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TreePart {
+    Log,
+    Leaves,
+}
+
+impl TreePart {
+    pub fn get_block_type(self) -> BlockType {
+        match self {
+            TreePart::Log => BlockType::from_name("log"),
+            TreePart::Leaves => BlockType::from_name("leaves"),
+        }
+    }
+}
+
+fn column_hash(seed: WorldSeed, x: isize, layer: u8) -> u64 {
+    let mut hasher = seed.hasher();
+    hasher.write_i64(x as i64);
+    hasher.write_u8(layer);
+    hasher.finish()
+}
+
+/// Whether the biome wants a tree at this column, before trees too close to each other are removed.
+/// Trees don't grow in lakes and rivers nor on their shores.
+fn is_tree_candidate(seed: WorldSeed, x: isize) -> bool {
+    if is_shore(seed, x) {
+        return false;
+    }
+    match biome_at(seed, x).get_tree_prob() {
+        0 => false,
+        prob => column_hash(seed, x, 0).is_multiple_of(prob as u64),
+    }
+}
+
+/// A tree stands on a candidate column when there is no other candidate on its left, close enough for their leaves to touch.
+pub fn is_tree_column(seed: WorldSeed, x: isize) -> bool {
+    is_tree_candidate(seed, x) && !(x - MIN_TREE_DISTANCE + 1..x).any(|left| is_tree_candidate(seed, left))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tree {
+    pub x: isize,
+    /// The row of the ground the tree grows on.
+    pub ground: isize,
+    /// Height of the trunk, in blocks.
+    pub height: isize,
+    /// Rows of the branches on the left and on the right of the trunk.
+    pub branches: [Option<isize>; 2],
+}
+
+impl Tree {
+    /// The tree growing on a column, if there is one.
+    pub fn at(seed: WorldSeed, x: isize) -> Option<Tree> {
+        if !is_tree_column(seed, x) {
+            return None;
+        }
+        let heights = biome_at(seed, x).get_tree_height();
+        let hash = column_hash(seed, x, 1);
+        let height = heights.start + (hash % (heights.end - heights.start) as u64) as isize;
+        let ground = surface_height(seed, x).floor().clamp(1.0, 2047.0) as isize;
+
+        // branches grow on the lower part of the trunk, under the leaves
+        let branch = |bits: u64| match height >= 6 && !bits.is_multiple_of(3) {
+            true => Some(ground - 2 - (bits / 3 % (height as u64 - 4)) as isize),
+            false => None,
+        };
+        Some(Tree {
+            x,
+            ground,
+            height,
+            branches: [branch(hash >> 16 & 0xff), branch(hash >> 24 & 0xff)],
+        })
+    }
+
+    pub fn top(&self) -> isize {
+        self.ground - self.height
+    }
+
+    /// The part of the tree at a position.
+    pub fn get(&self, (x, y): (isize, isize)) -> Option<TreePart> {
+        let (dx, dy) = (x - self.x, y - self.top());
+        if dx == 0 && (0..self.height).contains(&dy) {
+            return Some(TreePart::Log);
+        }
+        // the leaves are a rounded rectangle around the top of the trunk
+        if dx.abs() <= TREE_RADIUS && (-2..=1).contains(&dy) && !(dx.abs() == TREE_RADIUS && (dy == -2 || dy == 1)) {
+            return Some(TreePart::Leaves);
+        }
+        match self.branch_of((x, y)) {
+            Some((side, row)) if dx == side && y == row => Some(TreePart::Log),
+            Some(_) => Some(TreePart::Leaves),
+            None => None,
+        }
+    }
+
+    /// The side, -1 or 1, and the row of the branch a position belongs to.
+    /// Branches are under the leaves of the top of the trunk, so only the positions beside the trunk are checked.
+    fn branch_of(&self, (x, y): (isize, isize)) -> Option<(isize, isize)> {
+        let dx = x - self.x;
+        self.branches.iter().zip([-1, 1]).find_map(|(branch, side)| {
+            let row = (*branch)?;
+            let is_log = dx == side && y == row;
+            let is_leaves = (dx == side && y == row - 1) || (dx == 2 * side && (y == row || y == row - 1));
+            match is_log || is_leaves {
+                true => Some((side, row)),
+                false => None,
+            }
+        })
+    }
+
+    /// The parts of the tree which fall when the log at a position is cut.
+    /// Cutting the trunk fells the trunk above the cut with its leaves and the branches it holds, cutting a branch only fells its leaves.
+    pub fn parts_held_by(&self, (x, y): (isize, isize)) -> Vec<(isize, isize)> {
+        let cut_side = x - self.x;
+        let mut parts = Vec::new();
+        for part_x in self.x - TREE_RADIUS..=self.x + TREE_RADIUS {
+            for part_y in self.top() - 2..self.ground {
+                let falls = match (self.get((part_x, part_y)), self.branch_of((part_x, part_y))) {
+                    (None, _) => false,
+                    (Some(_), Some((side, row))) => (cut_side == 0 && row <= y) || side == cut_side,
+                    (Some(_), None) => cut_side == 0 && (part_x != self.x || part_y < y),
+                };
+                if falls && (part_x, part_y) != (x, y) {
+                    parts.push((part_x, part_y));
+                }
+            }
+        }
+        parts
+    }
+}
+
+/// The trees which may have parts in a column.
+pub fn trees_around(seed: WorldSeed, x: isize) -> Vec<Tree> {
+    (x - TREE_RADIUS..=x + TREE_RADIUS).filter_map(|x| Tree::at(seed, x)).collect()
+}
+
+impl World {
+    /// Removes the parts of a generated tree held by a log which was just broken, and returns what they drop.
+    /// Logs placed by the player or by structures don't belong to a tree and fell nothing.
+    pub fn fell_tree(&mut self, (x, y): (isize, isize)) -> Vec<Item> {
+        if structure_at(self.seed, (x, y)).is_some() {
+            return Vec::new();
+        }
+        let tree = match trees_around(self.seed, x).into_iter().find(|tree| tree.get((x, y)) == Some(TreePart::Log)) {
+            Some(tree) => tree,
+            None => return Vec::new(),
+        };
+        let mut items = Vec::new();
+        let mut felled = Vec::new();
+        for position in tree.parts_held_by((x, y)) {
+            // parts replaced by the player or by a structure stay in place
+            let block_type = self[position].block_type;
+            if tree.get(position).map(TreePart::get_block_type) != Some(block_type) || structure_at(self.seed, position).is_some() {
+                continue;
+            }
+            items.extend(block_type.as_item());
+            self.index_mut_and_render(position).block_type = BlockType::AIR;
+            self.queue_light_update(position);
+            felled.push(position);
+        }
+        self.spread_lights();
+        for position in felled {
+            self.wake_fluid(position);
+        }
+        items
+    }
+}
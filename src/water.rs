@@ -0,0 +1,184 @@
+//! Generated water: lakes and rivers filling basins carved in the surface, and aquifers flooding pockets underground.
+//!
+//! Like the structures, a body of water only depends on the seed and on its region, so that every section fills its own part of it.
+//! Water is generated at the levels where the fluid simulation leaves it still, so that nothing moves until the player digs around it.
+
+use crate::{
+    biomes::biome_at,
+    chunks::terrain_height,
+    seed::WorldSeed,
+    structures::{is_empty_region, REGION_WIDTH},
+};
+use std::hash::Hasher;
+
+/// Columns of each region holding at most one lake or river.
+pub const BASIN_REGION_WIDTH: isize = 128;
+const MAX_BASIN_RADIUS: isize = 24;
+/// Columns around a basin where the ground is made of sand.
+pub const SHORE_WIDTH: isize = 3;
+const BASIN_HASH_LAYER: u8 = 32;
+const AQUIFER_HASH_LAYER: u8 = 48;
+/// Rows where aquifers start, deep enough to never reach the surface.
+pub const AQUIFER_TOP: std::ops::Range<isize> = 100..400;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BasinKind {
+    Lake,
+    /// A river seen from the side is a narrow and steep channel.
+    River,
+}
+
+/// A hollow carved in the surface and filled with water up to its lowest edge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Basin {
+    pub kind: BasinKind,
+    /// Column of the deepest point.
+    pub x: isize,
+    pub radius: isize,
+    /// Blocks carved under the deepest point.
+    pub depth: f64,
+}
+
+fn region_hash(seed: WorldSeed, region: isize, layer: u8) -> u64 {
+    let mut hasher = seed.hasher();
+    hasher.write_i64(region as i64);
+    hasher.write_u8(layer);
+    hasher.finish()
+}
+
+impl Basin {
+    /// The basin of a region, if it has one.
+    pub fn in_region(seed: WorldSeed, region: isize) -> Option<Basin> {
+        let hash = region_hash(seed, region, BASIN_HASH_LAYER);
+        // the basin and its shores are contained in the region
+        let margin = MAX_BASIN_RADIUS + SHORE_WIDTH;
+        let x = region * BASIN_REGION_WIDTH + margin + (hash >> 8) as isize % (BASIN_REGION_WIDTH - 2 * margin);
+        match biome_at(seed, x).get_water_prob() {
+            0 => return None,
+            prob if !hash.is_multiple_of(prob) => return None,
+            _ => (),
+        }
+
+        let hash = region_hash(seed, region, BASIN_HASH_LAYER + 1);
+        let (kind, radii, depths) = match hash % 3 {
+            0 => (BasinKind::River, 5..8, 3..6),
+            _ => (BasinKind::Lake, 12..MAX_BASIN_RADIUS + 1, 4..10),
+        };
+        Some(Basin {
+            kind,
+            x,
+            radius: radii.start + (hash >> 8) as isize % (radii.end - radii.start),
+            depth: (depths.start + (hash >> 16) as isize % (depths.end - depths.start)) as f64,
+        })
+    }
+
+    /// Height of the ground of the lowest edge of the basin.
+    fn lowest_edge(&self, seed: WorldSeed) -> f64 {
+        terrain_height(seed, self.x - self.radius).max(terrain_height(seed, self.x + self.radius))
+    }
+
+    /// How many blocks deeper the basin makes the ground of a column where the terrain is at `terrain`.
+    /// The ground goes down to `depth` blocks under the lowest edge with a smooth profile,
+    /// so that the basin holds water even on a slope and its banks are never much steeper than the terrain around.
+    pub fn carving(&self, seed: WorldSeed, x: isize, terrain: f64) -> f64 {
+        let distance = (x - self.x).abs();
+        if distance >= self.radius {
+            return 0.0;
+        }
+        let weight = (1.0 + (std::f64::consts::PI * distance as f64 / self.radius as f64).cos()) / 2.0;
+        (self.lowest_edge(seed) + self.depth - terrain).max(0.0) * weight
+    }
+
+    pub fn columns(&self) -> std::ops::Range<isize> {
+        self.x - self.radius..self.x + self.radius + 1
+    }
+
+    /// The highest row of water, which is the ground of the lowest edge of the basin so that it never overflows.
+    pub fn water_level(&self, seed: WorldSeed) -> isize {
+        self.lowest_edge(seed).floor().clamp(1.0, 2047.0) as isize
+    }
+}
+
+/// The basin carved in a column, if there is one.
+pub fn basin_at(seed: WorldSeed, x: isize) -> Option<Basin> {
+    Basin::in_region(seed, x.div_euclid(BASIN_REGION_WIDTH)).filter(|basin| basin.columns().contains(&x))
+}
+
+/// The basins which may carve the columns from `start` to `end`, `end` excluded.
+pub fn basins_between(seed: WorldSeed, start: isize, end: isize) -> Vec<Basin> {
+    (start.div_euclid(BASIN_REGION_WIDTH)..=(end - 1).div_euclid(BASIN_REGION_WIDTH))
+        .filter_map(|region| Basin::in_region(seed, region))
+        .collect()
+}
+
+/// Whether a basin or its shores cross the columns from `start` to `end`, `end` excluded.
+pub fn is_near_water(seed: WorldSeed, start: isize, end: isize) -> bool {
+    basins_between(seed, start, end)
+        .iter()
+        .any(|basin| basin.x - basin.radius - SHORE_WIDTH < end && basin.x + basin.radius + SHORE_WIDTH >= start)
+}
+
+pub fn is_shore(seed: WorldSeed, x: isize) -> bool {
+    is_near_water(seed, x, x + 1)
+}
+
+/// A pocket of water deep underground, closed by walls of stone where it crosses caves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aquifer {
+    /// First column of the water.
+    pub x: isize,
+    pub width: isize,
+    /// Highest row of the water.
+    pub top: isize,
+    pub height: isize,
+}
+
+impl Aquifer {
+    /// Aquifers are placed in the regions left empty by structures, so that no structure is flooded.
+    pub fn in_region(seed: WorldSeed, region: isize) -> Option<Aquifer> {
+        if !is_empty_region(seed, region) {
+            return None;
+        }
+        let hash = region_hash(seed, region, AQUIFER_HASH_LAYER);
+        let width = 24 + (hash % 25) as isize;
+        let height = 10 + (hash >> 8) as isize % 11;
+        // the hashes only have 32 bits, all used above
+        let depth = region_hash(seed, region, AQUIFER_HASH_LAYER + 1);
+        Some(Aquifer {
+            // the walls are contained in the region too
+            x: region * REGION_WIDTH + 1 + (hash >> 16) as isize % (REGION_WIDTH - width - 2),
+            width,
+            top: AQUIFER_TOP.start + depth as isize % (AQUIFER_TOP.end - AQUIFER_TOP.start),
+            height,
+        })
+    }
+
+    /// Whether the aquifer carves a block: `Some(true)` in its pocket, `Some(false)` in the walls around its water, `None` where the caves are kept.
+    pub fn carves(&self, (x, y): (isize, isize)) -> Option<bool> {
+        let bottom = self.top + self.height;
+        let in_walls = (self.x - 1..=self.x + self.width).contains(&x) && (self.top..=bottom).contains(&y);
+        if in_walls && (x == self.x - 1 || x == self.x + self.width || y == bottom) {
+            return Some(false);
+        }
+        // the pocket is an ellipse going a few blocks over the water, which leaves some air to breathe
+        let (center_x, center_y) = (self.x as f64 + self.width as f64 / 2.0, (self.top - 3 + bottom) as f64 / 2.0);
+        let (radius_x, radius_y) = (self.width as f64 / 2.0 - 1.0, (bottom - self.top + 3) as f64 / 2.0);
+        let (dx, dy) = ((x as f64 + 0.5 - center_x) / radius_x, (y as f64 + 0.5 - center_y) / radius_y);
+        match dx * dx + dy * dy < 1.0 {
+            true => Some(true),
+            false => None,
+        }
+    }
+
+    /// Whether a carved block is under the water of the aquifer.
+    pub fn floods(&self, (x, y): (isize, isize)) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.top..self.top + self.height).contains(&y)
+    }
+}
+
+/// The aquifers which may be in the columns from `start` to `end`, `end` excluded.
+pub fn aquifers_between(seed: WorldSeed, start: isize, end: isize) -> Vec<Aquifer> {
+    (start.div_euclid(REGION_WIDTH)..=(end - 1).div_euclid(REGION_WIDTH))
+        .filter_map(|region| Aquifer::in_region(seed, region))
+        .collect()
+}
@@ -0,0 +1,355 @@
+use crate::{
+    blocks::{Block, BlockType, NaturalBackground},
+    chunks::{Chunk, SECTION_COUNT, SECTION_SIZE},
+    clock::Clock,
+    coords::{x_to_chunk, x_to_chunk_and_column},
+    falling::FallingBlock,
+    fluid::Fluid,
+    light::{BlockSet, Light},
+    player::{Player, MAX_BREATH},
+    save::{chunk_key, read_inventory, write_inventory, Reader, SaveError, Storage, Writer},
+    seed::WorldSeed,
+};
+use std::collections::{BTreeSet, VecDeque};
+
+/// The headless state of the game: chunks, light and fluids.
+/// Nothing here depends on a canvas so it runs the same way natively and in the browser.
+/// Renderers observe it through `blocks_to_render` and `light_to_render`.
+pub struct World {
+    pub seed: WorldSeed,
+    pub chunks: Vec<Chunk>,
+    pub first_chunk_number: isize,
+    pub air: Block,
+    pub clock: Clock,
+    /// Blocks from which light has to spread.
+    pub light_additions: VecDeque<(isize, isize)>,
+    /// Blocks which were darkened, with the light they had.
+    pub light_removals: VecDeque<(isize, isize, Light)>,
+    /// Blocks queued in `light_additions`, kept between updates so that it is only allocated again when chunks are loaded.
+    pub light_queued: BlockSet,
+    /// Blocks where fluids may move during the next tick.
+    pub fluid_update: BTreeSet<(isize, isize)>,
+    /// Blocks which left the grid to fall, not saved.
+    pub falling_blocks: Vec<FallingBlock>,
+    /// Fluid ticks run since the game started, which tell when lava moves.
+    pub fluid_ticks: u64,
+    /// Time not simulated yet by `update_fluids`, in seconds.
+    pub fluid_time: f64,
+    pub blocks_to_render: Vec<(isize, isize)>,
+    /// Blocks whose light changed, rendered a few at a time.
+    pub light_to_render: VecDeque<(isize, isize)>,
+    /// Where chunks are saved when unloaded and loaded from when they come back in range.
+    pub storage: Option<Box<dyn Storage>>,
+}
+
+/// How many sections are generated above and under the one of the player.
+pub const SECTION_DISTANCE: isize = 2;
+
+impl World {
+    pub fn new(seed: WorldSeed) -> World {
+        let mut chunks = Vec::new();
+        for _ in -5..5 {
+            chunks.push(Chunk::new());
+        }
+
+        let mut world = World::from_chunks(seed, -5, chunks);
+        world.update_sections(&Player::new());
+        world
+    }
+
+    /// Makes a world from chunks generated beforehand, the first one being `first_chunk_number`.
+    /// The fluids of the chunks are woken up and their light is computed.
+    pub fn from_chunks(seed: WorldSeed, first_chunk_number: isize, chunks: Vec<Chunk>) -> World {
+        let mut world = World {
+            seed,
+            chunks,
+            first_chunk_number,
+            air: Block {
+                block_type: BlockType::AIR,
+                natural_background: NaturalBackground::Sky,
+                light: Light::BLACK,
+                fluid: Fluid::Water,
+                fluid_level: 0,
+            },
+            clock: Clock::default(),
+            light_additions: VecDeque::with_capacity(2048),
+            light_removals: VecDeque::with_capacity(2048),
+            light_queued: BlockSet::default(),
+            fluid_update: BTreeSet::new(),
+            falling_blocks: Vec::new(),
+            fluid_ticks: 0,
+            fluid_time: 0.0,
+            blocks_to_render: Vec::with_capacity(2048),
+            light_to_render: VecDeque::with_capacity(2048),
+            storage: None,
+        };
+
+        for chunk_index in 0..world.chunks.len() {
+            world.init_fluids(chunk_index);
+        }
+        world.init_lights();
+
+        world
+    }
+
+    /// Generates the sections close enough to the player which are not generated yet.
+    pub fn update_sections(&mut self, player: &Player) {
+        let player_section = (player.y.floor() as isize).div_euclid(SECTION_SIZE as isize);
+        for section in player_section - SECTION_DISTANCE..=player_section + SECTION_DISTANCE {
+            if !(0..SECTION_COUNT as isize).contains(&section) {
+                continue;
+            }
+            for chunk_index in 0..self.chunks.len() {
+                if !self.chunks[chunk_index].is_generated(section as usize) {
+                    let chunk_number = self.first_chunk_number + chunk_index as isize;
+                    self.chunks[chunk_index].generate_section(self.seed, chunk_number, section as usize);
+                    self.prepare_section(chunk_index, section as usize);
+                }
+            }
+        }
+        self.spread_lights();
+    }
+
+    /// Queues the light and fluid updates needed by a section which just appeared in the world,
+    /// and renders its neighbours again since their borders depend on it.
+    fn prepare_section(&mut self, chunk_index: usize, section: usize) {
+        self.queue_section_lights(chunk_index, section);
+        self.queue_section_fluids(chunk_index, section);
+
+        let (x0, y0) = self.section_origin(chunk_index, section);
+        let size = SECTION_SIZE as isize;
+        for x in x0 - 1..=x0 + size {
+            self.blocks_to_render.push((x, y0 - 1));
+            self.blocks_to_render.push((x, y0 + size));
+        }
+        for y in y0..y0 + size {
+            self.blocks_to_render.push((x0 - 1, y));
+            self.blocks_to_render.push((x0 + size, y));
+        }
+    }
+
+    pub fn section_origin(&self, chunk_index: usize, section: usize) -> (isize, isize) {
+        (
+            (self.first_chunk_number + chunk_index as isize) * 32,
+            (section * SECTION_SIZE) as isize,
+        )
+    }
+
+    /// Activates the fluids of a section and of the blocks around it, which may now flow into it.
+    fn queue_section_fluids(&mut self, chunk_index: usize, section: usize) {
+        let (x0, y0) = self.section_origin(chunk_index, section);
+        let size = SECTION_SIZE as isize;
+        for x in x0 - 1..=x0 + size {
+            for y in y0 - 1..=y0 + size {
+                if self[(x, y)].fluid_level > 0 {
+                    self.fluid_update.insert((x, y));
+                }
+            }
+        }
+    }
+
+    /// Returns `false` outside of the loaded chunks and in sections which are not generated yet.
+    pub fn is_generated(&self, (x, y): (isize, isize)) -> bool {
+        let (chunk_number, column) = x_to_chunk_and_column(x);
+        let chunk_index = chunk_number - self.first_chunk_number;
+        y >= 0
+            && chunk_index >= 0
+            && self
+                .chunks
+                .get(chunk_index as usize)
+                .and_then(|chunk| chunk.get(column as usize, y as usize))
+                .is_some()
+    }
+
+    pub fn update_chunks(&mut self, player: &Player) {
+        let chunk_number = x_to_chunk(player.x.floor() as isize);
+
+        let mut diff = self.first_chunk_number - chunk_number;
+
+        while diff > -4 {
+            let removed_chunk = self.chunks.remove(self.chunks.len() - 1);
+            self.save_chunk(self.first_chunk_number + self.chunks.len() as isize, &removed_chunk);
+            self.first_chunk_number -= 1;
+            let chunk = self.load_chunk(self.first_chunk_number).unwrap_or_default();
+            self.chunks.insert(0, chunk);
+            self.prepare_chunk(0);
+
+            diff = self.first_chunk_number - chunk_number;
+        }
+        while diff < -4 {
+            let removed_chunk = self.chunks.remove(0);
+            self.save_chunk(self.first_chunk_number, &removed_chunk);
+            self.first_chunk_number += 1;
+            let new_chunk_number = self.first_chunk_number + self.chunks.len() as isize;
+            let chunk = self.load_chunk(new_chunk_number).unwrap_or_default();
+            self.chunks.push(chunk);
+            self.prepare_chunk(self.chunks.len() - 1);
+
+            diff = self.first_chunk_number - chunk_number;
+        }
+
+        self.update_sections(player);
+    }
+
+    /// Prepares the sections of a chunk loaded from the storage. The missing ones are generated by `update_sections`.
+    fn prepare_chunk(&mut self, chunk_index: usize) {
+        for section in 0..SECTION_COUNT {
+            if self.chunks[chunk_index].is_generated(section) {
+                self.prepare_section(chunk_index, section);
+            }
+        }
+    }
+
+    fn save_chunk(&mut self, chunk_number: isize, chunk: &Chunk) {
+        if let Some(storage) = &mut self.storage {
+            storage.write(&chunk_key(chunk_number), &chunk.serialize());
+        }
+    }
+
+    fn load_chunk(&self, chunk_number: isize) -> Option<Chunk> {
+        let data = self.storage.as_ref()?.read(&chunk_key(chunk_number))?;
+        // a corrupted chunk is generated again rather than making the game crash
+        Chunk::deserialize(&data).ok()
+    }
+
+    /// Writes the loaded chunks and the player to the storage.
+    /// Does nothing if the world has no storage.
+    pub fn save(&mut self, player: &Player) {
+        let mut writer = Writer::new();
+        writer.write_u32(self.seed.0);
+        writer.write_i64(self.first_chunk_number as i64);
+        writer.write_u32(self.chunks.len() as u32);
+        writer.write_f64(player.x);
+        writer.write_f64(player.y);
+        write_inventory(&mut writer, &player.inventory);
+        writer.write_u64(self.clock.ticks);
+        writer.write_u64(self.clock.day_length);
+        writer.write_f64(player.breath);
+
+        if let Some(storage) = &mut self.storage {
+            for (idx, chunk) in self.chunks.iter().enumerate() {
+                storage.write(&chunk_key(self.first_chunk_number + idx as isize), &chunk.serialize());
+            }
+            storage.write("world", &writer.data);
+        }
+    }
+
+    /// Restores a world saved with `World::save`. The storage is kept to save the world later.
+    pub fn load(storage: Box<dyn Storage>) -> Result<(World, Player), SaveError> {
+        let data = storage.read("world").ok_or(SaveError::Missing)?;
+        let mut reader = Reader::new(&data)?;
+        let seed = WorldSeed(reader.read_u32()?);
+        let first_chunk_number = reader.read_i64()? as isize;
+        let chunk_count = reader.read_u32()? as isize;
+        let mut player = Player::new();
+        player.x = reader.read_f64()?;
+        player.y = reader.read_f64()?;
+        player.inventory = read_inventory(&mut reader)?;
+        let ticks = reader.read_u64()?;
+        let day_length = reader.read_u64()?;
+        if day_length == 0 {
+            return Err(SaveError::Corrupted);
+        }
+        let clock = Clock::with_ticks(ticks, day_length);
+        player.breath = reader.read_f64()?;
+        if !(0.0..=MAX_BREATH).contains(&player.breath) {
+            return Err(SaveError::Corrupted);
+        }
+
+        let mut chunks = Vec::new();
+        for chunk_number in first_chunk_number..first_chunk_number + chunk_count {
+            let data = storage.read(&chunk_key(chunk_number)).ok_or(SaveError::Missing)?;
+            chunks.push(Chunk::deserialize(&data)?);
+        }
+
+        let mut world = World::from_chunks(seed, first_chunk_number, chunks);
+        world.clock = clock;
+        world.storage = Some(storage);
+
+        Ok((world, player))
+    }
+
+    /// Gives mutable access to a block and marks it and its neighbours as needing to be rendered again.
+    pub fn index_mut_and_render(&mut self, (x, y): (isize, isize)) -> &mut Block {
+        self.blocks_to_render.push((x, y));
+        self.blocks_to_render.push((x, y - 1));
+        self.blocks_to_render.push((x, y + 1));
+        self.blocks_to_render.push((x - 1, y));
+        self.blocks_to_render.push((x + 1, y));
+        &mut self[(x, y)]
+    }
+
+    pub fn init_fluids(&mut self, chunk_index: usize) {
+        for section in 0..SECTION_COUNT {
+            if self.chunks[chunk_index].is_generated(section) {
+                self.queue_section_fluids(chunk_index, section);
+            }
+        }
+    }
+}
+
+impl Default for World {
+    fn default() -> World {
+        World::new(WorldSeed::default())
+    }
+}
+
+impl std::ops::Index<(isize, isize)> for World {
+    type Output = Block;
+
+    fn index(&self, (x, y): (isize, isize)) -> &Self::Output {
+        let (chunk, column) = x_to_chunk_and_column(x);
+        let chunk_index = chunk - self.first_chunk_number;
+
+        if y >= 0 && chunk_index >= 0 {
+            if let Some(chunk) = self.chunks.get(chunk_index as usize) {
+                if let Some(block) = chunk.get(column as usize, y as usize) {
+                    return block;
+                }
+            }
+        }
+
+        &Block {
+            block_type: BlockType::AIR,
+            natural_background: NaturalBackground::Sky,
+            light: Light::BLACK,
+            fluid: Fluid::Water,
+            fluid_level: 0,
+        }
+    }
+}
+
+impl std::ops::IndexMut<(isize, isize)> for World {
+    fn index_mut(&mut self, (x, y): (isize, isize)) -> &mut Self::Output {
+        let (chunk_number, column) = x_to_chunk_and_column(x);
+        let chunk_index = chunk_number - self.first_chunk_number;
+
+        if y >= 0 && chunk_index >= 0 {
+            if let Some(chunk) = self.chunks.get_mut(chunk_index as usize) {
+                if let Some(block) = chunk.get_mut(column as usize, y as usize) {
+                    return block;
+                }
+            }
+        }
+
+        if self.air
+            != (Block {
+                block_type: BlockType::AIR,
+                natural_background: NaturalBackground::Sky,
+                light: Light::BLACK,
+                fluid: Fluid::Water,
+                fluid_level: 0,
+            })
+        {
+            self.air = Block {
+                block_type: BlockType::AIR,
+                natural_background: NaturalBackground::Sky,
+                light: Light::BLACK,
+                fluid: Fluid::Water,
+                fluid_level: 0,
+            };
+        }
+
+        &mut self.air
+    }
+}
@@ -0,0 +1,68 @@
+use terrarust::{
+    biomes::{biome_at, climate_at, Biome, Climate},
+    chunks::terrain_height,
+    seed::WorldSeed,
+    world::World,
+};
+
+const ALL_BIOMES: [Biome; 7] = [
+    Biome::Hills,
+    Biome::Grassland,
+    Biome::TemperateBroadleafForest,
+    Biome::Desert,
+    Biome::Snow,
+    Biome::Swamp,
+    Biome::Mountains,
+];
+
+#[test]
+fn biomes_follow_the_climate() {
+    let climate = |temperature, humidity| Climate { temperature, humidity }.get_biome();
+    assert_eq!(climate(0.8, -0.8), Biome::Desert);
+    assert_eq!(climate(0.8, 0.8), Biome::Swamp);
+    assert_eq!(climate(-0.8, 0.0), Biome::Snow);
+    assert_eq!(climate(-0.8, -0.8), Biome::Mountains);
+    assert_eq!(climate(0.0, 0.0), Biome::Grassland);
+    assert_eq!(climate(0.0, 0.8), Biome::TemperateBroadleafForest);
+    assert_eq!(climate(0.0, -0.8), Biome::Hills);
+
+    let seed = WorldSeed(4);
+    for x in (-5000..5000).step_by(37) {
+        let climate = climate_at(seed, x);
+        assert!((-1.0..=1.0).contains(&climate.temperature) && (-1.0..=1.0).contains(&climate.humidity));
+        assert_eq!(biome_at(seed, x), climate.get_biome());
+    }
+}
+
+#[test]
+fn every_biome_is_generated_in_large_areas() {
+    let seed = WorldSeed::default();
+    let biomes: Vec<Biome> = (-40_000..40_000).map(|x| biome_at(seed, x)).collect();
+    for biome in &ALL_BIOMES {
+        assert!(biomes.contains(biome), "no {:?} generated", biome);
+    }
+    let borders = biomes.windows(2).filter(|pair| pair[0] != pair[1]).count();
+    assert!(biomes.len() / borders > 100, "biomes are {} columns wide on average", biomes.len() / borders);
+}
+
+#[test]
+fn the_terrain_is_smooth_across_biome_borders() {
+    let seed = WorldSeed::default();
+    let borders: Vec<isize> = (-20_000..20_000).filter(|x| biome_at(seed, *x) != biome_at(seed, x + 1)).collect();
+    assert!(!borders.is_empty());
+    for x in borders {
+        for x in x - 4..x + 4 {
+            let step = (terrain_height(seed, x + 1) - terrain_height(seed, x)).abs();
+            assert!(step < 2.0, "the terrain jumps by {} at {}", step, x);
+        }
+    }
+}
+
+#[test]
+fn worlds_expose_biome_lookups() {
+    let world = World::new(WorldSeed(7));
+    for x in (-1000..1000).step_by(50) {
+        assert_eq!(world.get_biome(x), biome_at(WorldSeed(7), x));
+        assert_eq!(world.get_climate(x), climate_at(WorldSeed(7), x));
+    }
+}
@@ -0,0 +1,37 @@
+use terrarust::{
+    blocks::{BlockRegistry, BlockTexture, BlockType, BLOCKS},
+    items::Item,
+};
+
+#[test]
+fn block_definitions_are_valid() {
+    assert_eq!(BlockType::AIR.get_name(), "air");
+    assert!(BlockType::AIR.can_pass_through());
+    assert!(!BlockType::from_name("stone").can_pass_through());
+    assert_eq!(BlockType::from_name("grass").as_item(), vec![Item::from_name("dirt")]);
+    assert_eq!(Item::from_name("dirt").as_block(), Some(BlockType::from_name("dirt")));
+    assert_eq!(Item::from_name("coal").as_block(), None);
+
+    for idx in 0..BLOCKS.len() {
+        let block_type = BlockType(idx as u16);
+        assert_eq!(BlockType::from_name(block_type.get_name()), block_type);
+        if let Some(BlockTexture::Atlas { atlas }) = &block_type.get_properties().texture {
+            assert_eq!(atlas.0, 0, "{} does not leave room for its 16 tiles", block_type.get_name());
+        }
+    }
+}
+
+#[test]
+fn invalid_definitions_are_rejected() {
+    let block = |name: &str| {
+        format!(
+            r#"{{"name": "{}", "solid": true, "light_loss": 1, "light_emission": [0, 0, 0], "hardness": 1.0, "drops": [], "texture": null}}"#,
+            name
+        )
+    };
+
+    assert!(BlockRegistry::from_json(&format!("[{}, {}]", block("air"), block("dirt"))).is_ok());
+    assert!(BlockRegistry::from_json(&format!("[{}, {}]", block("dirt"), block("air"))).is_err());
+    assert!(BlockRegistry::from_json(&format!("[{}, {}, {}]", block("air"), block("dirt"), block("dirt"))).is_err());
+    assert!(BlockRegistry::from_json("[{\"name\": \"air\"}]").is_err());
+}
@@ -0,0 +1,119 @@
+use terrarust::{
+    blocks::{BlockType, NaturalBackground},
+    caves::{generate_caves, MIN_CAVE_SIZE},
+    chunks::{soil_at, surface_height, Chunk, Section, CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_SIZE},
+    seed::WorldSeed,
+};
+
+const CHUNKS: std::ops::Range<isize> = -4..4;
+const SECTIONS: std::ops::Range<usize> = 0..10;
+
+/// Generates the caves of `SECTIONS` in `CHUNKS` as one area, row by row.
+fn cave_area(seed: WorldSeed) -> (Vec<bool>, usize) {
+    let width = CHUNKS.len() * CHUNK_WIDTH;
+    let mut area = vec![false; width * SECTIONS.len() * SECTION_SIZE];
+    for (chunk_idx, chunk_number) in CHUNKS.enumerate() {
+        for section in SECTIONS {
+            let caves = generate_caves(seed, chunk_number, section);
+            for column in 0..CHUNK_WIDTH {
+                for row in 0..SECTION_SIZE {
+                    let (x, y) = (chunk_idx * CHUNK_WIDTH + column, section * SECTION_SIZE + row);
+                    area[y * width + x] = caves[column * SECTION_SIZE + row];
+                }
+            }
+        }
+    }
+    (area, width)
+}
+
+/// Returns the size of each pocket of the area and whether it touches its sides or its bottom.
+fn pockets(area: &[bool], width: usize) -> Vec<(usize, bool)> {
+    let height = area.len() / width;
+    let mut visited = vec![false; area.len()];
+    let mut pockets = Vec::new();
+    for start in 0..area.len() {
+        if !area[start] || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![start];
+        let mut size = 0;
+        let mut touches_edges = false;
+        while let Some(idx) = stack.pop() {
+            size += 1;
+            let (x, y) = (idx % width, idx / width);
+            touches_edges |= x == 0 || x == width - 1 || y == height - 1;
+            let mut neighbours = Vec::new();
+            if x > 0 {
+                neighbours.push(idx - 1);
+            }
+            if x + 1 < width {
+                neighbours.push(idx + 1);
+            }
+            if y > 0 {
+                neighbours.push(idx - width);
+            }
+            if y + 1 < height {
+                neighbours.push(idx + width);
+            }
+            for neighbour in neighbours {
+                if area[neighbour] && !visited[neighbour] {
+                    visited[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+        pockets.push((size, touches_edges));
+    }
+    pockets
+}
+
+#[test]
+fn caves_are_reproducible() {
+    for chunk_number in CHUNKS {
+        for section in SECTIONS {
+            assert_eq!(generate_caves(WorldSeed(5), chunk_number, section), generate_caves(WorldSeed(5), chunk_number, section));
+            assert_eq!(Section::generate(WorldSeed(5), chunk_number, section), Section::generate(WorldSeed(5), chunk_number, section));
+        }
+    }
+    assert_ne!(cave_area(WorldSeed(5)).0, cave_area(WorldSeed(6)).0);
+}
+
+#[test]
+fn caves_are_connected() {
+    for seed in 0..4 {
+        let (area, width) = cave_area(WorldSeed(seed));
+        let pockets = pockets(&area, width);
+        let carved: usize = pockets.iter().map(|(size, _)| size).sum();
+        assert!(carved > area.len() / 50, "seed {}: only {} blocks are carved", seed, carved);
+
+        // pockets are never cut by chunk borders into parts too small to be kept
+        for (size, touches_edges) in &pockets {
+            assert!(*touches_edges || *size >= MIN_CAVE_SIZE, "seed {}: pocket of {} blocks", seed, size);
+        }
+
+        // most of the carved blocks belong to tunnels large enough to be explored
+        let in_large_caves: usize = pockets.iter().map(|(size, _)| size).filter(|size| **size >= 100).sum();
+        assert!(in_large_caves * 2 > carved, "seed {}: {} of {} blocks are in large caves", seed, in_large_caves, carved);
+    }
+}
+
+#[test]
+fn caves_stay_underground() {
+    let seed = WorldSeed(5);
+    for chunk_number in CHUNKS {
+        let chunk = Chunk::generate(seed, chunk_number);
+        for (column, x) in (0..CHUNK_WIDTH).zip(chunk_number * 32..) {
+            let surface = surface_height(seed, x).floor() as usize;
+            let (surface_block, _) = soil_at(seed, x);
+            assert_eq!(chunk.get(column, surface).unwrap().block_type, BlockType::from_name(surface_block));
+            for y in surface..surface + 5 {
+                assert_ne!(chunk.get(column, y).unwrap().block_type, BlockType::AIR);
+            }
+            for y in surface..CHUNK_HEIGHT {
+                assert_eq!(chunk.get(column, y).unwrap().natural_background, NaturalBackground::Dirt);
+            }
+        }
+    }
+}
+
@@ -0,0 +1,67 @@
+use terrarust::{
+    blocks::BlockType,
+    clock::{Clock, NIGHT_LIGHT, TICKS_PER_SECOND},
+    light::MAX_LIGHT,
+    seed::WorldSeed,
+    world::World,
+};
+
+#[test]
+fn days_go_through_day_and_night() {
+    let mut clock = Clock::new(1000);
+    assert_eq!(clock.daylight(), MAX_LIGHT);
+
+    clock.advance(450);
+    assert_eq!(clock.time_of_day(), 0.75);
+    assert!(NIGHT_LIGHT < clock.daylight() && clock.daylight() < MAX_LIGHT);
+
+    clock.advance(200);
+    assert_eq!(clock.daylight(), NIGHT_LIGHT);
+    assert_eq!(clock.day(), 0);
+
+    clock.advance(1000);
+    assert_eq!(clock.daylight(), NIGHT_LIGHT);
+    assert_eq!(clock.day(), 1);
+}
+
+#[test]
+fn the_clock_does_not_depend_on_the_frame_rate() {
+    let second = TICKS_PER_SECOND as u64;
+    for fps in [30, 60, 144, 240].iter() {
+        let mut clock = Clock::new(1000);
+        let start = clock.ticks;
+        for _ in 0..fps * 10 {
+            clock.update(1.0 / *fps as f64);
+        }
+        let ticks = clock.ticks - start;
+        assert!((10 * second - 1..=10 * second).contains(&ticks), "{} ticks in 10 seconds at {} fps", ticks, fps);
+    }
+
+    // a throttled tab gets a few long frames
+    let mut clock = Clock::new(1000);
+    let start = clock.ticks;
+    clock.update(2.5);
+    clock.update(2.5);
+    assert_eq!(clock.ticks - start, 5 * second);
+}
+
+#[test]
+fn sunlight_follows_the_clock() {
+    let mut world = World::new(WorldSeed::default());
+    world.clock = Clock::new(1000);
+    let surface = (0..).find(|y| world[(0, *y)].block_type != BlockType::AIR).unwrap();
+    let sky = (0, surface - 1);
+    assert_eq!(world.get_light_color(sky), [MAX_LIGHT; 3]);
+
+    // a buried torch keeps its color at night while the sky gets dark
+    let torch = (0, surface + 20);
+    world.index_mut_and_render(torch).block_type = BlockType::from_name("torch");
+    world.update_light(torch);
+    let emission = BlockType::from_name("torch").get_light_emission();
+    world.clock.advance(500);
+    assert_eq!(world.get_light_color(sky), [NIGHT_LIGHT; 3]);
+    assert_eq!(world.get_light_color(torch), [emission.red, emission.green, emission.blue]);
+
+    world.clock.advance(500);
+    assert_eq!(world.get_light_color(sky), [MAX_LIGHT; 3]);
+}
@@ -0,0 +1,54 @@
+//! Fixtures shared by the integration tests.
+// each test file only uses some of them
+#![allow(dead_code)]
+
+use std::ops::Range;
+use terrarust::{blocks::BlockType, fluid::FLUID_FULL, player::Player, world::World};
+
+/// First row of the areas carved by the tests, deep enough to be under the surface.
+pub const TOP: isize = 100;
+
+/// A world with a block of stone, from column -5 to 30 and from `TOP - 5` to `TOP + 40`, in which shapes can be carved far from any cave.
+pub fn stone_world() -> World {
+    let mut world = World::default();
+    let mut player = Player::new();
+    player.y = TOP as f64 + 20.0;
+    world.update_sections(&player);
+    for x in -5..30 {
+        for y in TOP - 5..TOP + 40 {
+            world[(x, y)].block_type = BlockType::from_name("stone");
+            world[(x, y)].fluid_level = 0;
+        }
+    }
+    world.fluid_update.clear();
+    world
+}
+
+/// A world whose chunks and sections around a position are generated, as if the player stood there.
+pub fn world_around((x, y): (isize, isize)) -> World {
+    let mut world = World::default();
+    let mut player = Player::new();
+    player.x = x as f64;
+    player.y = y as f64;
+    world.update_chunks(&player);
+    world.update_sections(&player);
+    world
+}
+
+pub fn carve(world: &mut World, xs: Range<isize>, ys: Range<isize>) {
+    for x in xs {
+        for y in ys.clone() {
+            world[(x, y)].block_type = BlockType::AIR;
+        }
+    }
+}
+
+/// Fills an area with water and wakes it up.
+pub fn pour(world: &mut World, xs: Range<isize>, ys: Range<isize>) {
+    for x in xs {
+        for y in ys.clone() {
+            world[(x, y)].fluid_level = FLUID_FULL;
+            world.wake_fluid((x, y));
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+use terrarust::{
+    biomes::biome_at,
+    blocks::BlockType,
+    export::{block_colors, generate_region, render_region, ExportOptions, BIOME_BAND_HEIGHT},
+    seed::WorldSeed,
+    software_renderer::SoftwareTextures,
+};
+
+fn args(text: &str) -> Vec<String> {
+    text.split_whitespace().map(String::from).collect()
+}
+
+fn textures() -> SoftwareTextures {
+    SoftwareTextures::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("pkg"))
+}
+
+#[test]
+fn arguments_are_parsed() {
+    let options = ExportOptions::parse(&args("7 -2..3 --rows 32..96 --output test.png --settle --no-light --no-biomes")).unwrap();
+    assert_eq!(
+        options,
+        ExportOptions {
+            seed: WorldSeed(7),
+            chunks: -2..3,
+            rows: 32..96,
+            output: String::from("test.png"),
+            settle_fluids: true,
+            lighting: false,
+            biome_band: false,
+        }
+    );
+
+    let options = ExportOptions::parse(&args("hello 0..1")).unwrap();
+    assert_eq!(options.seed, WorldSeed::parse("hello"));
+    assert_eq!(options.rows, 0..256);
+    assert_eq!(options.output, "map.png");
+    assert!(options.lighting && options.biome_band && !options.settle_fluids);
+
+    for invalid in ["", "42", "42 0..1 5..6", "42 1..1", "42 0..x", "42 0..1 --rows", "42 0..1 --rows 0..4096", "42 0..1 --fast"].iter() {
+        assert!(ExportOptions::parse(&args(invalid)).is_err(), "{:?} is accepted", invalid);
+    }
+}
+
+#[test]
+fn maps_show_blocks_and_biomes() {
+    let options = ExportOptions::parse(&args("42 -1..1 --rows 0..128 --no-light")).unwrap();
+    let textures = textures();
+    let world = generate_region(&options);
+    let image = render_region(&world, &options, &textures);
+    assert_eq!((image.width, image.height), (64, 128 + BIOME_BAND_HEIGHT));
+
+    for (x, column) in (-32..32).zip(0..) {
+        let biome = biome_at(options.seed, x);
+        let [red, green, blue, _] = image.get_pixel(column, 0);
+        assert_eq!([red, green, blue], biome.get_map_color());
+    }
+
+    let stone = BlockType::from_name("stone");
+    let stone_color = block_colors(&textures)[stone.0 as usize].unwrap();
+    let (x, y) = (-32..32)
+        .flat_map(|x| (0..128).map(move |y| (x, y)))
+        .find(|position| world[*position].block_type == stone && world[*position].fluid_level == 0)
+        .unwrap();
+    let [red, green, blue, _] = image.get_pixel((x + 32) as u32, y as u32 + BIOME_BAND_HEIGHT);
+    assert_eq!([red, green, blue], stone_color);
+}
+
+#[test]
+fn maps_are_lit() {
+    let options = ExportOptions::parse(&args("42 0..1 --rows 0..256 --no-biomes")).unwrap();
+    let world = generate_region(&options);
+    let image = render_region(&world, &options, &textures());
+    // the sky is lit and the stone deep underground is not
+    assert_ne!(image.get_pixel(0, 0), [0, 0, 0, 255]);
+    let dark = (0..32)
+        .flat_map(|x| (200..256).map(move |y| (x, y)))
+        .find(|position| world.get_light_color(*position) == [0, 0, 0])
+        .unwrap();
+    assert_eq!(image.get_pixel(dark.0 as u32, dark.1 as u32), [0, 0, 0, 255]);
+}
+
+#[test]
+fn fluids_can_be_settled_before_the_export() {
+    let options = ExportOptions::parse(&args("42 0..1 --rows 0..256 --settle")).unwrap();
+    let world = generate_region(&options);
+    assert!(world.fluid_ticks > 0);
+    assert!(world.are_fluids_settled());
+}
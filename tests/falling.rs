@@ -0,0 +1,126 @@
+mod common;
+
+use common::{carve, pour, stone_world, TOP};
+use terrarust::{
+    blocks::BlockType,
+    biomes::{biome_at, Biome},
+    chunks::{Chunk, CHUNK_HEIGHT},
+    fluid::FLUID_FULL,
+    seed::WorldSeed,
+    world::World,
+};
+
+/// A world with a 20 blocks deep shaft carved in stone.
+fn shaft() -> World {
+    let mut world = stone_world();
+    carve(&mut world, 0..3, TOP..TOP + 20);
+    world
+}
+
+fn fall(world: &mut World) {
+    for _ in 0..1000 {
+        if world.falling_blocks.is_empty() {
+            return;
+        }
+        world.update_falling_blocks();
+    }
+    panic!("the blocks never landed");
+}
+
+#[test]
+fn unsupported_sand_falls_and_lands() {
+    let mut world = shaft();
+    let (sand, gravel, stone) = (BlockType::from_name("sand"), BlockType::from_name("gravel"), BlockType::from_name("stone"));
+    world[(1, TOP)].block_type = stone;
+    world[(1, TOP - 1)].block_type = sand;
+    world[(1, TOP - 2)].block_type = gravel;
+    world[(1, TOP - 3)].block_type = stone;
+
+    world.check_support((1, TOP - 1));
+    assert!(world.falling_blocks.is_empty(), "supported blocks fell");
+
+    world.index_mut_and_render((1, TOP)).block_type = BlockType::AIR;
+    world.check_support((1, TOP - 1));
+    assert_eq!(world.falling_blocks.len(), 2);
+    assert_eq!(world[(1, TOP - 1)].block_type, BlockType::AIR);
+    assert_eq!(world[(1, TOP - 2)].block_type, BlockType::AIR);
+    // stone doesn't fall
+    assert_eq!(world[(1, TOP - 3)].block_type, stone);
+
+    fall(&mut world);
+    assert_eq!(world[(1, TOP + 19)].block_type, sand);
+    assert_eq!(world[(1, TOP + 18)].block_type, gravel);
+    assert_eq!(world[(1, TOP + 17)].block_type, BlockType::AIR);
+}
+
+#[test]
+fn falling_blocks_update_the_light() {
+    let mut world = shaft();
+    let sand = BlockType::from_name("sand");
+    world[(0, TOP + 10)].block_type = BlockType::from_name("torch");
+    world[(1, TOP - 1)].block_type = sand;
+    world.init_lights();
+    let lit = world[(1, TOP + 19)].light;
+
+    world.check_support((1, TOP - 1));
+    fall(&mut world);
+    assert_eq!(world[(1, TOP + 19)].block_type, sand);
+    assert_ne!(world[(1, TOP + 19)].light, lit);
+    let spread = world[(1, TOP + 19)].light;
+    world.init_lights();
+    assert_eq!(world[(1, TOP + 19)].light, spread);
+}
+
+#[test]
+fn blocks_landing_in_water_push_it_up() {
+    let mut world = shaft();
+    let sand = BlockType::from_name("sand");
+    pour(&mut world, 0..3, TOP + 18..TOP + 20);
+    let water = |world: &World| (0..3).flat_map(|x| (TOP..TOP + 20).map(move |y| (x, y))).map(|position| world[position].fluid_level as usize).sum::<usize>();
+    world[(1, TOP - 1)].block_type = sand;
+    world.check_support((1, TOP - 1));
+    fall(&mut world);
+    assert_eq!(world[(1, TOP + 19)].block_type, sand);
+    assert_eq!(world[(1, TOP + 19)].fluid_level, 0);
+    assert_eq!(water(&world), 6 * FLUID_FULL as usize);
+
+    for _ in 0..200 {
+        world.tick_fluids();
+    }
+    assert_eq!(water(&world), 6 * FLUID_FULL as usize);
+}
+
+#[test]
+fn blocks_landing_in_a_full_column_wait_for_room() {
+    let mut world = shaft();
+    let (sand, stone) = (BlockType::from_name("sand"), BlockType::from_name("stone"));
+    world[(1, TOP - 1)].block_type = sand;
+    world.check_support((1, TOP - 1));
+    // the shaft is filled while the sand falls
+    for y in 0..TOP + 20 {
+        if world.is_generated((1, y)) {
+            world[(1, y)].block_type = stone;
+        }
+    }
+    for _ in 0..10 {
+        world.update_falling_blocks();
+    }
+    assert_eq!(world.falling_blocks.len(), 1);
+    assert_eq!(world[(1, -1)].block_type, BlockType::AIR);
+
+    world.index_mut_and_render((1, TOP - 3)).block_type = BlockType::AIR;
+    fall(&mut world);
+    assert_eq!(world[(1, TOP - 3)].block_type, sand);
+}
+
+#[test]
+fn deserts_are_covered_with_sand() {
+    let seed = WorldSeed::default();
+    let chunk_number = (0..1000).find(|chunk| (0..32).all(|column| biome_at(seed, chunk * 32 + column) == Biome::Desert)).unwrap();
+    let chunk = Chunk::generate(seed, chunk_number);
+    let sand = BlockType::from_name("sand");
+    for column in 0..32 {
+        let surface = (0..CHUNK_HEIGHT).find(|y| chunk.get(column, *y).unwrap().block_type != BlockType::AIR).unwrap();
+        assert_eq!(chunk.get(column, surface).unwrap().block_type, sand);
+    }
+}
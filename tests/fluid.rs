@@ -0,0 +1,209 @@
+mod common;
+
+use common::{carve, pour, stone_world, TOP};
+use terrarust::{
+    blocks::BlockType,
+    fluid::{Fluid, FLUID_FULL, LAVA_SLOWNESS},
+    light::Light,
+    world::World,
+};
+
+fn pour_lava(world: &mut World, xs: std::ops::Range<isize>, ys: std::ops::Range<isize>) {
+    for x in xs {
+        for y in ys.clone() {
+            world[(x, y)].fluid = Fluid::Lava;
+            world[(x, y)].fluid_level = FLUID_FULL;
+            world.wake_fluid((x, y));
+        }
+    }
+}
+
+fn total_water(world: &World) -> usize {
+    (-5..30).flat_map(|x| (TOP - 5..TOP + 40).map(move |y| (x, y))).map(|position| world[position].fluid_level as usize).sum()
+}
+
+/// Ticks until the water stops moving and returns the number of ticks.
+fn settle(world: &mut World) -> usize {
+    for tick in 0..20_000 {
+        if world.are_fluids_settled() {
+            return tick;
+        }
+        world.tick_fluids();
+    }
+    panic!("the water never settled");
+}
+
+/// Height of the water in a column, in blocks, from the bottom of a container.
+fn water_height(world: &World, x: isize, bottom: isize) -> f64 {
+    (TOP..bottom).map(|y| world[(x, y)].fluid_level.min(FLUID_FULL) as f64 / FLUID_FULL as f64).sum()
+}
+
+#[test]
+fn water_falls_and_settles() {
+    let mut world = stone_world();
+    carve(&mut world, 0..10, TOP..TOP + 10);
+    pour(&mut world, 0..10, TOP..TOP + 3);
+    let total = total_water(&world);
+
+    settle(&mut world);
+    assert_eq!(total_water(&world), total);
+    for x in 0..10 {
+        assert!((water_height(&world, x, TOP + 10) - 3.0).abs() < 0.2);
+        assert_eq!(world[(x, TOP + 5)].fluid_level, 0);
+        assert!(world[(x, TOP + 9)].fluid_level >= FLUID_FULL);
+    }
+}
+
+#[test]
+fn water_spreads_until_it_is_flat() {
+    let mut world = stone_world();
+    carve(&mut world, 0..20, TOP..TOP + 10);
+    pour(&mut world, 0..2, TOP..TOP + 10);
+    let total = total_water(&world);
+
+    settle(&mut world);
+    assert_eq!(total_water(&world), total);
+    let heights: Vec<f64> = (0..20).map(|x| water_height(&world, x, TOP + 10)).collect();
+    for height in &heights {
+        assert!((height - 1.0).abs() < 0.2, "{:?}", heights);
+    }
+}
+
+#[test]
+fn both_sides_of_a_u_tube_reach_the_same_height() {
+    let mut world = stone_world();
+    // two tubes joined by their bottom
+    carve(&mut world, 0..2, TOP..TOP + 30);
+    carve(&mut world, 10..12, TOP..TOP + 30);
+    carve(&mut world, 0..12, TOP + 28..TOP + 30);
+    pour(&mut world, 0..2, TOP + 4..TOP + 30);
+    pour(&mut world, 2..12, TOP + 28..TOP + 30);
+    let total = total_water(&world);
+
+    settle(&mut world);
+    assert_eq!(total_water(&world), total);
+    let (left, right) = (water_height(&world, 0, TOP + 28), water_height(&world, 11, TOP + 28));
+    // part of the water is held by the pressure at the bottom
+    assert!(right > 8.0, "the water did not go up the right tube ({} blocks)", right);
+    assert!((left - right).abs() <= 1.0, "left {} right {}", left, right);
+}
+
+#[test]
+fn settled_water_is_no_longer_simulated() {
+    let mut world = stone_world();
+    carve(&mut world, 0..10, TOP..TOP + 10);
+    pour(&mut world, 0..10, TOP + 9..TOP + 10);
+    assert!(settle(&mut world) < 5);
+
+    // digging lets the water fall again
+    world.index_mut_and_render((4, TOP + 10)).block_type = BlockType::AIR;
+    world.wake_fluid((4, TOP + 10));
+    assert!(!world.are_fluids_settled());
+    world.tick_fluids();
+    assert!(world[(4, TOP + 10)].fluid_level > 0);
+}
+
+#[test]
+fn fluids_tick_at_a_fixed_rate() {
+    let mut worlds: Vec<World> = (0..3).map(|_| stone_world()).collect();
+    for world in worlds.iter_mut() {
+        carve(world, 0..20, TOP..TOP + 10);
+        pour(world, 0..3, TOP..TOP + 6);
+    }
+
+    // the same second at 20, 10 and 1 frames per second, where a slow frame doesn't run more than a few ticks
+    for _ in 0..20 {
+        worlds[0].update_fluids(0.05);
+    }
+    for _ in 0..10 {
+        worlds[1].update_fluids(0.1);
+    }
+    worlds[2].update_fluids(1.0);
+
+    let mut ticked = stone_world();
+    carve(&mut ticked, 0..20, TOP..TOP + 10);
+    pour(&mut ticked, 0..3, TOP..TOP + 6);
+    let levels = |world: &World| -> Vec<u16> {
+        (0..20).flat_map(|x| (TOP..TOP + 10).map(move |y| (x, y))).map(|position| world[position].fluid_level).collect()
+    };
+    for tick in 1..=20 {
+        ticked.tick_fluids();
+        if tick == 4 {
+            assert_eq!(levels(&worlds[2]), levels(&ticked));
+        }
+    }
+    assert_eq!(levels(&worlds[0]), levels(&ticked));
+    assert_eq!(levels(&worlds[1]), levels(&ticked));
+}
+
+#[test]
+fn lava_flows_slower_than_water() {
+    let mut water = stone_world();
+    carve(&mut water, 0..20, TOP..TOP + 10);
+    pour(&mut water, 0..2, TOP + 9..TOP + 10);
+    let mut lava = stone_world();
+    carve(&mut lava, 0..20, TOP..TOP + 10);
+    pour_lava(&mut lava, 0..2, TOP + 9..TOP + 10);
+
+    let water_ticks = settle(&mut water);
+    let lava_ticks = settle(&mut lava);
+    assert!(lava_ticks >= water_ticks * LAVA_SLOWNESS as usize / 2, "water {} lava {}", water_ticks, lava_ticks);
+    assert!(lava[(10, TOP + 9)].fluid_level > 0);
+    assert_eq!(lava[(10, TOP + 9)].fluid, Fluid::Lava);
+}
+
+#[test]
+fn lava_touching_water_turns_into_stone() {
+    let mut world = stone_world();
+    carve(&mut world, 0..20, TOP..TOP + 10);
+    pour(&mut world, 0..3, TOP + 9..TOP + 10);
+    pour_lava(&mut world, 17..20, TOP + 9..TOP + 10);
+    settle(&mut world);
+
+    let stone = BlockType::from_name("stone");
+    let cooled: Vec<isize> = (3..17).filter(|x| world[(*x, TOP + 9)].block_type == stone).collect();
+    assert!(!cooled.is_empty());
+    // no water is left next to lava
+    for x in 0..19 {
+        let (left, right) = (world[(x, TOP + 9)], world[(x + 1, TOP + 9)]);
+        if left.fluid_level > 0 && right.fluid_level > 0 {
+            assert_eq!(left.fluid, right.fluid);
+        }
+    }
+}
+
+#[test]
+fn lava_lights_its_surroundings() {
+    let mut world = stone_world();
+    carve(&mut world, 0..10, TOP..TOP + 10);
+    world.init_lights();
+    assert_eq!(world[(8, TOP + 5)].light, Light::BLACK);
+
+    // the lava falls to the bottom, lighting the blocks it goes through
+    pour_lava(&mut world, 0..10, TOP..TOP + 1);
+    settle(&mut world);
+    assert_eq!(world[(8, TOP)].fluid_level, 0);
+    let light = world[(8, TOP + 5)].light;
+    assert!(light.red > light.blue, "{:?}", light);
+}
+
+#[test]
+fn water_spreads_symmetrically() {
+    let mut world = stone_world();
+    carve(&mut world, 0..21, TOP..TOP + 5);
+    pour(&mut world, 10..11, TOP..TOP + 3);
+    for tick in 0..200 {
+        world.tick_fluids();
+        for distance in 1..=10 {
+            for y in TOP..TOP + 5 {
+                assert_eq!(
+                    world[(10 - distance, y)].fluid_level,
+                    world[(10 + distance, y)].fluid_level,
+                    "the water is not symmetric at tick {}",
+                    tick
+                );
+            }
+        }
+    }
+    assert!(world[(0, TOP + 4)].fluid_level > 0);
+}
@@ -0,0 +1,31 @@
+use std::path::Path;
+use terrarust::{
+    blocks::{BlockType, BLOCKS},
+    items::{Item, ItemRegistry, ITEMS},
+    software_renderer::SoftwareTextures,
+    textures::Sprite,
+};
+
+#[test]
+fn every_item_has_an_icon() {
+    let textures = SoftwareTextures::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("pkg"));
+    for item in ITEMS.iter() {
+        assert_eq!(Item::from_name(item.get_name()), item);
+        assert!(textures.get(Sprite::Item(item)).width > 0, "the icon of {} is missing", item.get_name());
+    }
+    // the drops of the blocks are read from the registry
+    for idx in 0..BLOCKS.len() {
+        for item in BlockType(idx as u16).as_item() {
+            assert!(item.0 < ITEMS.len() as u16);
+        }
+    }
+}
+
+#[test]
+fn invalid_item_definitions_are_rejected() {
+    let item = |name: &str| format!(r#"{{"name": "{}", "icon": "ressources/items/{}.png"}}"#, name, name);
+
+    assert!(ItemRegistry::from_json(&format!("[{}, {}]", item("dirt"), item("log"))).is_ok());
+    assert!(ItemRegistry::from_json(&format!("[{}, {}]", item("dirt"), item("dirt"))).is_err());
+    assert!(ItemRegistry::from_json("[{\"name\": \"dirt\"}]").is_err());
+}
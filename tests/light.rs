@@ -0,0 +1,182 @@
+use terrarust::{
+    blocks::BlockType,
+    chunks::{CHUNK_HEIGHT, CHUNK_WIDTH},
+    light::{interpolate_corners, Light, MAX_LIGHT},
+    player::Player,
+    world::World,
+};
+
+/// Light of every generated block of the world.
+fn lights(world: &World) -> Vec<Light> {
+    world
+        .chunks
+        .iter()
+        .flat_map(|chunk| (0..CHUNK_WIDTH).flat_map(move |x| (0..CHUNK_HEIGHT).filter_map(move |y| chunk.get(x, y))))
+        .map(|block| block.light)
+        .collect()
+}
+
+/// Checks that the light spread after some changes is the one computed from scratch.
+fn assert_lights_are_consistent(world: &mut World) {
+    let spread = lights(world);
+    world.init_lights();
+    let computed = lights(world);
+    let differences = spread.iter().zip(computed.iter()).filter(|(a, b)| a != b).count();
+    assert_eq!(differences, 0, "{} blocks have a different light", differences);
+}
+
+fn surface(world: &World, x: isize) -> isize {
+    (0..).find(|y| world[(x, *y)].block_type != BlockType::AIR).unwrap()
+}
+
+/// Generates the sections around a depth.
+fn generate_around(world: &mut World, y: isize) {
+    let mut player = Player::new();
+    player.y = y as f64;
+    world.update_sections(&player);
+}
+
+#[test]
+fn removing_blocks_lets_light_in() {
+    let mut world = World::default();
+    let surface = surface(&world, 0);
+    let bottom = surface + 12;
+    let dark = world[(0, bottom)].light.brightness();
+
+    for y in surface..=bottom {
+        world.index_mut_and_render((0, y)).block_type = BlockType::AIR;
+        world.update_light((0, y));
+    }
+
+    assert!(world[(0, bottom)].light.brightness() > dark);
+    assert_lights_are_consistent(&mut world);
+}
+
+#[test]
+fn placing_blocks_casts_shadows() {
+    let mut world = World::default();
+    let roof = (-3..=3).map(|x| surface(&world, x)).min().unwrap() - 4;
+    let lit = world[(0, roof + 1)].light.brightness();
+
+    for x in -3..=3 {
+        world.index_mut_and_render((x, roof)).block_type = BlockType::from_name("stone");
+        world.update_light((x, roof));
+    }
+
+    assert!(world[(0, roof + 1)].light.brightness() < lit);
+    assert_lights_are_consistent(&mut world);
+}
+
+#[test]
+fn removing_a_placed_block_restores_the_light() {
+    let mut world = World::default();
+    let before = lights(&world);
+    let position = (5, surface(&world, 5) - 2);
+
+    world.index_mut_and_render(position).block_type = BlockType::from_name("stone");
+    world.update_light(position);
+    assert_ne!(lights(&world), before);
+
+    world.index_mut_and_render(position).block_type = BlockType::AIR;
+    world.update_light(position);
+    assert_eq!(lights(&world), before);
+}
+
+#[test]
+fn torches_light_the_underground() {
+    let mut world = World::default();
+    let position = (0, surface(&world, 0) + 20);
+    generate_around(&mut world, position.1);
+    let (before, block_type) = (lights(&world), world[position].block_type);
+    assert_eq!(world[position].light, Light::BLACK);
+
+    world.index_mut_and_render(position).block_type = BlockType::from_name("torch");
+    world.update_light(position);
+    assert_eq!(world[position].light, BlockType::from_name("torch").get_light_emission());
+    assert!(!world[(position.0, position.1 + 1)].light.is_black());
+    assert_lights_are_consistent(&mut world);
+
+    world.index_mut_and_render(position).block_type = block_type;
+    world.update_light(position);
+    assert_eq!(lights(&world), before);
+}
+
+#[test]
+fn colored_light_spreads_per_channel() {
+    let mut world = World::default();
+    let surface = surface(&world, 0);
+    let torch = BlockType::from_name("torch");
+    let emission = torch.get_light_emission();
+    assert!(emission.red > emission.blue);
+
+    // a torch at the end of a dark tunnel, far from the sky
+    let tunnel: Vec<(isize, isize)> = (0..8).map(|x| (x, surface + 25)).collect();
+    generate_around(&mut world, surface + 25);
+    for position in &tunnel {
+        world.index_mut_and_render(*position).block_type = BlockType::AIR;
+        world.update_light(*position);
+    }
+    world.index_mut_and_render(tunnel[0]).block_type = torch;
+    world.update_light(tunnel[0]);
+
+    for (distance, position) in tunnel.iter().enumerate() {
+        let expected = emission.saturating_sub(distance as u8 * BlockType::AIR.get_light_loss());
+        assert_eq!(world[*position].light, expected);
+    }
+    assert_lights_are_consistent(&mut world);
+}
+
+#[test]
+fn sunlight_falls_down_shafts() {
+    let mut world = World::default();
+    let surface = surface(&world, 0);
+    let bottom = surface + 40;
+    generate_around(&mut world, bottom);
+    assert_eq!(world[(0, surface - 1)].light.sun, MAX_LIGHT);
+
+    for y in surface..=bottom {
+        world.index_mut_and_render((0, y)).block_type = BlockType::AIR;
+        world.update_light((0, y));
+    }
+    assert_eq!(world[(0, bottom)].light.sun, MAX_LIGHT);
+    assert_lights_are_consistent(&mut world);
+
+    // a roof stops the sunlight, which only diffuses from the sides below it
+    world.index_mut_and_render((0, surface)).block_type = BlockType::from_name("stone");
+    world.update_light((0, surface));
+    assert!(world[(0, surface + 1)].light.sun < MAX_LIGHT);
+    assert!(world[(0, bottom)].light.sun < world[(0, surface + 1)].light.sun);
+    assert_lights_are_consistent(&mut world);
+}
+
+#[test]
+fn corners_average_the_blocks_around_them() {
+    let mut world = World::default();
+    let (x, y) = (3, surface(&world, 3));
+    world.index_mut_and_render((x, y - 1)).block_type = BlockType::from_name("torch");
+    world.update_light((x, y - 1));
+
+    let daylight = world.clock.daylight();
+    let around = [(x - 1, y - 1), (x, y - 1), (x - 1, y), (x, y)].map(|position| world[position].light.color(daylight));
+    for channel in 0..3 {
+        let sum: usize = around.iter().map(|color| color[channel] as usize).sum();
+        assert_eq!(world.get_corner_light((x, y))[channel] as usize, (sum + 2) / 4);
+    }
+
+    let corners = world.get_corner_lights((x, y));
+    assert_eq!(corners[0], world.get_corner_light((x, y)));
+    assert_eq!(corners[3], world.get_corner_light((x + 1, y + 1)));
+    // in the open sky every corner is as bright as the sky
+    assert_eq!(world.get_corner_lights((x, 2)), [[MAX_LIGHT; 3]; 4]);
+}
+
+#[test]
+fn smooth_light_is_interpolated_between_corners() {
+    let corners = [[0, 0, 0], [100, 0, 0], [0, 100, 0], [100, 100, 40]];
+    assert_eq!(interpolate_corners(corners, (0.0, 0.0)), corners[0]);
+    assert_eq!(interpolate_corners(corners, (1.0, 0.0)), corners[1]);
+    assert_eq!(interpolate_corners(corners, (0.0, 1.0)), corners[2]);
+    assert_eq!(interpolate_corners(corners, (1.0, 1.0)), corners[3]);
+    assert_eq!(interpolate_corners(corners, (0.5, 0.5)), [50, 50, 10]);
+    assert_eq!(interpolate_corners(corners, (0.25, 0.5)), [25, 50, 5]);
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use common::{carve, pour, stone_world, TOP};
+use terrarust::{
+    items::Item,
+    player::{Player, MAX_BREATH},
+    world::World,
+};
+
+const NO_KEYS: (bool, bool, bool, bool) = (false, false, false, false);
+
+/// A world with a 10 blocks wide and 20 blocks deep pool carved in stone, filled with water from `water_top`.
+fn pool(water_top: isize) -> World {
+    let mut world = stone_world();
+    carve(&mut world, 0..10, TOP..TOP + 20);
+    pour(&mut world, 0..10, water_top..TOP + 20);
+    world
+}
+
+/// A player standing at the bottom of the pool.
+fn diver() -> Player {
+    let mut player = Player::new();
+    player.x = 4.0;
+    player.y = TOP as f64 + 19.99;
+    player
+}
+
+#[test]
+fn players_float_to_the_surface() {
+    let world = pool(TOP + 5);
+    let mut player = diver();
+    for frame in 0..600 {
+        player.handle_events(NO_KEYS, &world, frame);
+    }
+    assert!(player.y < TOP as f64 + 16.0, "the player stayed at {}", player.y);
+    assert!(!player.is_head_under_water(&world));
+    assert!(player.submersion(&world) > 0.0);
+}
+
+#[test]
+fn the_jump_key_swims_upward() {
+    let world = pool(TOP);
+    let (mut floating, mut swimming) = (diver(), diver());
+    for frame in 0..30 {
+        floating.handle_events(NO_KEYS, &world, frame);
+        swimming.handle_events((true, false, false, false), &world, frame);
+    }
+    assert!(swimming.y < floating.y - 1.0, "swimming {} floating {}", swimming.y, floating.y);
+}
+
+#[test]
+fn water_slows_the_player_down() {
+    let (dry, wet) = (pool(TOP + 20), pool(TOP));
+    let (mut walking, mut swimming) = (diver(), diver());
+    for frame in 0..20 {
+        walking.handle_events((false, true, false, false), &dry, frame);
+        swimming.handle_events((false, true, false, false), &wet, frame);
+    }
+    assert!(swimming.x - 4.0 < (walking.x - 4.0) * 0.75, "swimming {} walking {}", swimming.x, walking.x);
+}
+
+#[test]
+fn breath_runs_out_under_water() {
+    let mut world = pool(TOP);
+    let mut player = diver();
+    player.breathe(&world, MAX_BREATH - 1.0);
+    assert_eq!(player.breath, 1.0);
+
+    for x in 0..10 {
+        for y in TOP..TOP + 20 {
+            world[(x, y)].fluid_level = 0;
+        }
+    }
+    player.breathe(&world, 1.0);
+    assert!(player.breath > 2.0 && player.breath < MAX_BREATH);
+    player.breathe(&world, MAX_BREATH);
+    assert_eq!(player.breath, MAX_BREATH);
+}
+
+#[test]
+fn breath_does_not_depend_on_the_frame_rate() {
+    let world = pool(TOP);
+    let (mut slow, mut fast) = (diver(), diver());
+    for _ in 0..30 {
+        slow.breathe(&world, 1.0 / 30.0);
+    }
+    for _ in 0..120 {
+        fast.breathe(&world, 1.0 / 120.0);
+    }
+    assert!((slow.breath - (MAX_BREATH - 1.0)).abs() < 1e-9);
+    assert!((fast.breath - slow.breath).abs() < 1e-9);
+}
+
+#[test]
+fn drowning_players_respawn() {
+    let world = pool(TOP);
+    let mut player = diver();
+    player.inventory.push(Item::from_name("coal"));
+    let mut frames = 0;
+    // the breath is full again only once the player respawned
+    while frames == 0 || player.breath < MAX_BREATH {
+        assert!((0.0..10.0).contains(&player.x), "the player left the pool at frame {}", frames);
+        assert!(frames <= 601, "the player is still in the pool after {} frames", frames);
+        player.handle_events(NO_KEYS, &world, frames);
+        player.breathe(&world, 1.0 / 60.0);
+        frames += 1;
+    }
+    assert!((599..=601).contains(&frames), "the player drowned after {} frames", frames);
+    assert_eq!((player.x, player.y), (Player::new().x, Player::new().y));
+    assert_eq!(player.breath, MAX_BREATH);
+    assert!(!player.is_drowning());
+    assert_eq!(player.inventory[1], Some((Item::from_name("coal"), 1)));
+}
+
+#[test]
+fn harder_blocks_take_longer_to_mine() {
+    let world = stone_world();
+    let mut player = Player::new();
+    let stone = (0, TOP + 1);
+    let hardness = world[stone].block_type.get_hardness();
+    assert!(hardness > 0.0);
+
+    let frames = (0..).take_while(|_| !player.mine(stone, &world, 1.0 / 60.0)).count();
+    assert!((frames as f64 - hardness * 60.0).abs() <= 1.0);
+
+    // moving to another block starts over
+    assert!(!player.mine(stone, &world, hardness / 2.0));
+    assert!(!player.mine((1, TOP + 1), &world, hardness / 2.0 + 0.01));
+    player.stop_mining();
+    assert!(!player.mine((1, TOP + 1), &world, hardness / 2.0 + 0.01));
+    assert!(player.mine((1, TOP + 1), &world, hardness / 2.0 + 0.01));
+}
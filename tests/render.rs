@@ -0,0 +1,79 @@
+use std::{path::Path, rc::Rc};
+use terrarust::{
+    map::Map,
+    player::Player,
+    renderer::Renderer,
+    software_renderer::{RgbaImage, SoftwareRenderer, SoftwareTextures},
+    world::World,
+};
+
+/// Compares a frame with a PNG stored in `tests/golden`.
+/// Run with `UPDATE_GOLDEN=1` to write the current output as the new reference.
+fn assert_matches_golden(image: &RgbaImage, name: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+
+    if std::env::var("UPDATE_GOLDEN").is_ok() || !path.exists() {
+        std::fs::write(&path, image.encode_png()).unwrap();
+        return;
+    }
+
+    let golden = RgbaImage::decode_png(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!((golden.width, golden.height), (image.width, image.height));
+    let different_pixels = golden
+        .pixels
+        .chunks(4)
+        .zip(image.pixels.chunks(4))
+        .filter(|(a, b)| a != b)
+        .count();
+    assert_eq!(different_pixels, 0, "{} differs from the rendered frame", name);
+}
+
+#[test]
+fn frame_matches_golden_image() {
+    let textures = Rc::new(SoftwareTextures::load(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("pkg"),
+    ));
+    let mut screen = SoftwareRenderer::new(640, 480, textures);
+    let screen_center = (320, 240);
+
+    let mut world = World::default();
+    let mut player = Player::new();
+    for frame in 0..200 {
+        player.handle_events((false, false, false, false), &world, frame);
+    }
+
+    let mut map = Map::new(&screen, &mut world);
+    map.update(&mut world, &player);
+    map.draw_on_canvas(&mut screen, &world, &player, screen_center);
+    player.draw_on_canvas(&mut screen, screen_center, 200);
+
+    assert_eq!(screen.get_size(), (640, 480));
+    assert_matches_golden(&screen.to_image(), "frame.png");
+}
+
+#[test]
+fn night_frame_matches_golden_image() {
+    let textures = Rc::new(SoftwareTextures::load(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("pkg"),
+    ));
+    let mut screen = SoftwareRenderer::new(640, 480, textures);
+    let screen_center = (320, 240);
+
+    let mut world = World::default();
+    let mut player = Player::new();
+    for frame in 0..200 {
+        player.handle_events((false, false, false, false), &world, frame);
+    }
+
+    // the sections are rendered during the day then the night falls
+    let mut map = Map::new(&screen, &mut world);
+    map.update(&mut world, &player);
+    world.clock.advance(world.clock.day_length / 2);
+    map.update(&mut world, &player);
+    map.draw_on_canvas(&mut screen, &world, &player, screen_center);
+    player.draw_on_canvas(&mut screen, screen_center, 200);
+
+    assert_matches_golden(&screen.to_image(), "night_frame.png");
+}
@@ -0,0 +1,159 @@
+use terrarust::{
+    blocks::BlockType,
+    chunks::{CHUNK_HEIGHT, CHUNK_WIDTH},
+    clock::Clock,
+    fluid::{Fluid, FLUID_FULL},
+    items::Item,
+    player::Player,
+    save::{decode_base64, encode_base64, MemoryStorage, Reader, SaveError, Writer, SAVE_VERSION},
+    seed::WorldSeed,
+    world::World,
+};
+
+#[test]
+fn load_restores_an_identical_world() {
+    let mut world = World::new(WorldSeed::default());
+    world.storage = Some(Box::new(MemoryStorage::default()));
+    world.index_mut_and_render((3, 40)).block_type = BlockType::AIR;
+    world.index_mut_and_render((4, 10)).block_type = BlockType::from_name("dirt");
+    world[(5, 12)].fluid_level = 15;
+    world[(6, 12)].fluid = Fluid::Lava;
+    world[(6, 12)].fluid_level = FLUID_FULL;
+
+    let mut player = Player::new();
+    player.x = 12.25;
+    player.y = 30.5;
+    player.inventory.push(Item::from_name("log"));
+    player.inventory.push(Item::from_name("log"));
+    player.inventory.push(Item::from_name("dirt"));
+    world.save(&player);
+
+    let (loaded_world, loaded_player) = World::load(world.storage.take().unwrap()).unwrap();
+    assert_eq!(loaded_world.first_chunk_number, world.first_chunk_number);
+    assert_eq!(loaded_world.chunks.len(), world.chunks.len());
+    for (loaded_chunk, chunk) in loaded_world.chunks.iter().zip(world.chunks.iter()) {
+        for x in 0..CHUNK_WIDTH {
+            for y in 0..CHUNK_HEIGHT {
+                match (loaded_chunk.get(x, y), chunk.get(x, y)) {
+                    (Some(loaded_block), Some(block)) => {
+                        assert_eq!(loaded_block.block_type, block.block_type);
+                        assert_eq!(loaded_block.natural_background, block.natural_background);
+                        assert_eq!(loaded_block.fluid, block.fluid);
+                        assert_eq!(loaded_block.fluid_level, block.fluid_level);
+                    }
+                    (None, None) => (),
+                    _ => panic!("({}, {}) is generated in only one of the worlds", x, y),
+                }
+            }
+        }
+    }
+
+    assert_eq!(loaded_player.x, 12.25);
+    assert_eq!(loaded_player.y, 30.5);
+    assert_eq!(loaded_player.inventory.len(), player.inventory.len());
+    for idx in 0..player.inventory.len() {
+        assert_eq!(loaded_player.inventory[idx], player.inventory[idx]);
+    }
+}
+
+#[test]
+fn unloaded_chunks_are_restored() {
+    let mut world = World::new(WorldSeed::default());
+    world.storage = Some(Box::new(MemoryStorage::default()));
+    world.index_mut_and_render((-150, 5)).block_type = BlockType::from_name("dirt");
+
+    let mut player = Player::new();
+    player.x = 200.0;
+    world.update_chunks(&player);
+    assert!(world.first_chunk_number > -5);
+
+    player.x = -20.0;
+    world.update_chunks(&player);
+    assert_eq!(world.first_chunk_number, -5);
+    assert_eq!(world[(-150, 5)].block_type, BlockType::from_name("dirt"));
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let mut world = World::new(WorldSeed::default());
+    world.storage = Some(Box::new(MemoryStorage::default()));
+    world.save(&Player::new());
+
+    let mut storage = world.storage.take().unwrap();
+    let mut data = storage.read("world").unwrap();
+    data[4..6].copy_from_slice(&(SAVE_VERSION + 1).to_le_bytes());
+    storage.write("world", &data);
+
+    assert_eq!(
+        World::load(storage).err(),
+        Some(SaveError::UnsupportedVersion(SAVE_VERSION + 1))
+    );
+    assert_eq!(World::load(Box::new(MemoryStorage::default())).err(), Some(SaveError::Missing));
+}
+
+#[test]
+fn the_seed_is_saved() {
+    let mut world = World::new(WorldSeed::parse("terrarust"));
+    world.storage = Some(Box::new(MemoryStorage::default()));
+    world.save(&Player::new());
+
+    let (loaded_world, _player) = World::load(world.storage.take().unwrap()).unwrap();
+    assert_eq!(loaded_world.seed, world.seed);
+}
+
+#[test]
+fn the_clock_is_saved() {
+    let mut world = World::new(WorldSeed::default());
+    world.clock = Clock::new(600);
+    world.clock.advance(1234);
+    world.storage = Some(Box::new(MemoryStorage::default()));
+    world.save(&Player::new());
+
+    let (loaded_world, _player) = World::load(world.storage.take().unwrap()).unwrap();
+    assert_eq!(loaded_world.clock, world.clock);
+}
+
+#[test]
+fn the_breath_is_saved() {
+    let mut world = World::new(WorldSeed::default());
+    let mut player = Player::new();
+    player.breath = 1.25;
+    world.storage = Some(Box::new(MemoryStorage::default()));
+    world.save(&player);
+
+    let (_world, loaded_player) = World::load(world.storage.take().unwrap()).unwrap();
+    assert_eq!(loaded_player.breath, 1.25);
+}
+
+#[test]
+fn long_names_are_not_truncated() {
+    let name = "long_name_".repeat(40);
+    let mut writer = Writer::new();
+    writer.write_str(&name);
+    writer.write_u8(7);
+
+    let mut reader = Reader::new(&writer.data).unwrap();
+    assert_eq!(reader.read_str().unwrap(), name);
+    assert_eq!(reader.read_u8().unwrap(), 7);
+}
+
+#[test]
+fn entries_are_encoded_in_base64() {
+    assert_eq!(encode_base64(b""), "");
+    assert_eq!(encode_base64(b"f"), "Zg==");
+    assert_eq!(encode_base64(b"fo"), "Zm8=");
+    assert_eq!(encode_base64(b"foo"), "Zm9v");
+    assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+
+    let mut world = World::new(WorldSeed::default());
+    world.storage = Some(Box::new(MemoryStorage::default()));
+    world.save(&Player::new());
+    let data = world.storage.as_ref().unwrap().read("chunk/0").unwrap();
+    let encoded = encode_base64(&data);
+    assert!(encoded.len() <= data.len() * 4 / 3 + 4);
+    assert_eq!(decode_base64(&encoded), Some(data));
+
+    assert_eq!(decode_base64("Zm9"), None);
+    assert_eq!(decode_base64("Zg==Zm8="), None);
+    assert_eq!(decode_base64("Z!9v"), None);
+}
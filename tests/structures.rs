@@ -0,0 +1,141 @@
+use terrarust::{
+    blocks::BlockType,
+    chunks::{surface_height, Chunk, Section, CHUNK_WIDTH, SECTION_SIZE},
+    coords::x_to_chunk_and_column,
+    player::Player,
+    seed::WorldSeed,
+    structures::{structure_at, PrefabRegistry, Structure, PREFABS},
+    world::World,
+};
+
+/// The first structure east of the spawn built from a prefab and crossing the border between two chunks.
+fn find_structure(seed: WorldSeed, prefab: &str) -> Structure {
+    let prefab = PREFABS.get_by_name(prefab).unwrap();
+    (0..1000)
+        .filter_map(|region| Structure::in_region(seed, region))
+        .find(|structure| {
+            let (_, column) = x_to_chunk_and_column(structure.x);
+            structure.prefab == prefab && column as usize + structure.get_prefab().width > CHUNK_WIDTH
+        })
+        .unwrap()
+}
+
+fn chests(structure: &Structure) -> Vec<(isize, isize)> {
+    let prefab = structure.get_prefab();
+    let chest = BlockType::from_name("chest");
+    (0..prefab.width as isize)
+        .flat_map(|x| (0..prefab.height as isize).map(move |y| (x, y)))
+        .map(|(x, y)| (structure.x + x, structure.y + y))
+        .filter(|position| structure.get(*position) == Some(chest))
+        .collect()
+}
+
+#[test]
+fn prefabs_are_loaded() {
+    for name in ["cabin", "ruins", "treasure_room"].iter() {
+        let prefab = &PREFABS[PREFABS.get_by_name(name).unwrap()];
+        assert!(!prefab.loot.is_empty(), "{} has no loot", name);
+    }
+}
+
+#[test]
+fn invalid_prefabs_are_rejected() {
+    let prefab = |rows: &str, palette: &str| {
+        let json = format!(r#"[{{ "name": "test", "weight": 1, "depth": [0, 0], "ground": 0, "palette": {}, "rows": {} }}]"#, palette, rows);
+        PrefabRegistry::from_json(&json)
+    };
+    assert!(prefab(r#"["SS", " S"]"#, r#"{ "S": "stone" }"#).is_ok());
+    assert!(prefab(r#"["SS", "S"]"#, r#"{ "S": "stone" }"#).is_err());
+    assert!(prefab(r#"["SX"]"#, r#"{ "S": "stone" }"#).is_err());
+    assert!(prefab(r#"["SS"]"#, r#"{ "S": "marble" }"#).is_err());
+    assert!(prefab(r#"[]"#, r#"{ "S": "stone" }"#).is_err());
+}
+
+#[test]
+fn structures_rest_at_their_depth() {
+    let seed = WorldSeed(5);
+    let structures: Vec<Structure> = (-200..200).filter_map(|region| Structure::in_region(seed, region)).collect();
+    assert!(structures.len() > 100);
+    for structure in &structures {
+        let prefab = structure.get_prefab();
+        let ground = structure.y + prefab.ground as isize;
+        let surface = (structure.x..structure.x + prefab.width as isize)
+            .map(|x| surface_height(seed, x).floor() as isize)
+            .max()
+            .unwrap();
+        assert!((prefab.depth.0..=prefab.depth.1).contains(&(ground - surface)), "{} is at the wrong depth", prefab.name);
+    }
+    for name in ["cabin", "ruins", "treasure_room"].iter() {
+        let prefab = PREFABS.get_by_name(name).unwrap();
+        assert!(structures.iter().any(|structure| structure.prefab == prefab), "no {} generated", name);
+    }
+}
+
+#[test]
+fn structures_are_stamped_across_chunk_borders() {
+    let seed = WorldSeed::default();
+    for name in ["cabin", "ruins"].iter() {
+        let structure = find_structure(seed, name);
+        let (first_chunk, _) = x_to_chunk_and_column(structure.x);
+        for chunk_number in first_chunk..=first_chunk + 1 {
+            let mut chunk = Chunk::new();
+            for y in structure.rows() {
+                let section = y as usize / SECTION_SIZE;
+                if !chunk.is_generated(section) {
+                    chunk.set_section(section, Section::generate(seed, chunk_number, section));
+                }
+                for column in 0..CHUNK_WIDTH {
+                    let x = chunk_number * CHUNK_WIDTH as isize + column as isize;
+                    if let Some(block_type) = structure.get((x, y)) {
+                        assert_eq!(chunk.get(column, y as usize).unwrap().block_type, block_type);
+                        assert_eq!(chunk.get(column, y as usize).unwrap().fluid_level, 0);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn structures_do_not_depend_on_where_they_are_approached_from() {
+    let seed = WorldSeed::default();
+    let structure = find_structure(seed, "ruins");
+    let approach = |from: isize| {
+        let mut world = World::new(seed);
+        let mut player = Player::new();
+        player.y = structure.y as f64;
+        for step in 0..=4 {
+            player.x = (from + (structure.x - from) * step / 4) as f64;
+            world.update_chunks(&player);
+        }
+        world
+    };
+    let (from_left, from_right) = (approach(structure.x - 400), approach(structure.x + 400));
+    for x in structure.x - 10..structure.x + 30 {
+        for y in structure.rows() {
+            assert_eq!(from_left[(x, y)].block_type, from_right[(x, y)].block_type);
+        }
+    }
+}
+
+#[test]
+fn chests_hold_loot() {
+    let seed = WorldSeed::default();
+    let world = World::new(seed);
+    let structure = find_structure(seed, "treasure_room");
+    let prefab = structure.get_prefab();
+    let positions = chests(&structure);
+    assert_eq!(positions.len(), 2);
+    assert!(positions.iter().any(|position| !world.open_chest(*position).is_empty()));
+    for position in positions {
+        assert_eq!(structure_at(seed, position), Some(structure));
+        let items = world.open_chest(position);
+        assert_eq!(items, world.open_chest(position));
+        for entry in &prefab.loot {
+            let count = items.iter().filter(|item| **item == entry.item).count();
+            assert!(count == 0 || (entry.count.0..=entry.count.1).contains(&count));
+        }
+        assert!(items.iter().all(|item| prefab.loot.iter().any(|entry| entry.item == *item)));
+    }
+    assert!(world.open_chest((structure.x, structure.y)).is_empty());
+}
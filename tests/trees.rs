@@ -0,0 +1,140 @@
+mod common;
+
+use common::world_around;
+use terrarust::{
+    blocks::BlockType,
+    chunks::{Chunk, CHUNK_WIDTH},
+    coords::x_to_chunk_and_column,
+    items::Item,
+    seed::WorldSeed,
+    trees::{is_tree_column, Tree, TreePart, MIN_TREE_DISTANCE},
+};
+
+/// The first tree with a branch on its right east of the spawn of the default world.
+fn branched_tree() -> Tree {
+    (0..2000).filter_map(|x| Tree::at(WorldSeed::default(), x)).find(|tree| tree.branches[1].is_some()).unwrap()
+}
+
+#[test]
+fn trees_are_made_of_logs_and_leaves() {
+    let tree = branched_tree();
+    let (chunk_number, column) = x_to_chunk_and_column(tree.x);
+    let chunk = Chunk::generate(WorldSeed::default(), chunk_number);
+    let block = |y: isize| chunk.get(column as usize, y as usize).unwrap().block_type;
+    let (log, leaves) = (TreePart::Log.get_block_type(), TreePart::Leaves.get_block_type());
+
+    for y in tree.top()..tree.ground {
+        assert_eq!(block(y), log);
+    }
+    assert_eq!(block(tree.top() - 1), leaves);
+    assert_ne!(block(tree.ground), log);
+    let branch = tree.branches[1].unwrap();
+    assert_eq!(tree.get((tree.x + 1, branch)), Some(TreePart::Log));
+    assert_eq!(tree.get((tree.x + 2, branch)), Some(TreePart::Leaves));
+}
+
+/// Trees only depend on the seed, so the browser and the native tools place them on the same columns.
+#[test]
+fn trees_are_placed_the_same_on_every_platform() {
+    let seed = WorldSeed(42);
+    let trees: Vec<isize> = (-100..200).filter(|x| is_tree_column(seed, *x)).collect();
+    assert_eq!(trees, vec![-40, -9, 13, 59, 69, 83, 99, 161, 178, 199]);
+    let tree = Tree::at(seed, -40).unwrap();
+    assert_eq!((tree.ground, tree.height, tree.branches), (38, 10, [None, None]));
+}
+
+#[test]
+fn trees_are_far_enough_not_to_touch() {
+    let seed = WorldSeed(3);
+    let trees: Vec<isize> = (-2000..2000).filter(|x| is_tree_column(seed, *x)).collect();
+    assert!(trees.len() > 50);
+    for pair in trees.windows(2) {
+        assert!(pair[1] - pair[0] >= MIN_TREE_DISTANCE, "trees at {} and {}", pair[0], pair[1]);
+    }
+}
+
+#[test]
+fn trees_cross_chunk_borders() {
+    // a tree close to the border of its chunk has leaves in the next one
+    let seed = WorldSeed::default();
+    let tree = (-3000..3000)
+        .filter(|x| x_to_chunk_and_column(*x).1 as usize == CHUNK_WIDTH - 1)
+        .find_map(|x| Tree::at(seed, x))
+        .unwrap();
+    let (chunk_number, _) = x_to_chunk_and_column(tree.x + 1);
+    let chunk = Chunk::generate(seed, chunk_number);
+    assert_eq!(chunk.get(0, tree.top() as usize).unwrap().block_type, TreePart::Leaves.get_block_type());
+}
+
+#[test]
+fn felling_a_trunk_topples_the_tree() {
+    let tree = branched_tree();
+    let mut world = world_around((tree.x, tree.ground));
+    let (log, leaves) = (TreePart::Log.get_block_type(), TreePart::Leaves.get_block_type());
+
+    let cut = tree.ground - 1;
+    world.index_mut_and_render((tree.x, cut)).block_type = BlockType::AIR;
+    let items = world.fell_tree((tree.x, cut));
+    assert!(items.contains(&Item::from_name("log")));
+    assert!(items.contains(&Item::from_name("foliage")));
+    for x in tree.x - 3..=tree.x + 3 {
+        for y in tree.top() - 3..=cut {
+            let block_type = world[(x, y)].block_type;
+            assert!(block_type != log && block_type != leaves, "({}, {}) was not felled", x, y);
+        }
+    }
+}
+
+#[test]
+fn felling_keeps_the_stump() {
+    let tree = branched_tree();
+    let mut world = world_around((tree.x, tree.ground));
+    let log = TreePart::Log.get_block_type();
+
+    let cut = tree.top() + 1;
+    world.index_mut_and_render((tree.x, cut)).block_type = BlockType::AIR;
+    world.fell_tree((tree.x, cut));
+    assert_eq!(world[(tree.x, tree.top())].block_type, BlockType::AIR);
+    for y in cut + 1..tree.ground {
+        assert_eq!(world[(tree.x, y)].block_type, log);
+    }
+}
+
+#[test]
+fn felling_a_branch_only_drops_its_leaves() {
+    let tree = branched_tree();
+    let mut world = world_around((tree.x, tree.ground));
+    let (log, leaves) = (TreePart::Log.get_block_type(), TreePart::Leaves.get_block_type());
+
+    let branch = tree.branches[1].unwrap();
+    let branch_leaves = [(tree.x + 1, branch - 1), (tree.x + 2, branch), (tree.x + 2, branch - 1)];
+    world.index_mut_and_render((tree.x + 1, branch)).block_type = BlockType::AIR;
+    let items = world.fell_tree((tree.x + 1, branch));
+    assert_eq!(items, vec![Item::from_name("foliage"); 3]);
+    for position in branch_leaves.iter() {
+        assert_eq!(world[*position].block_type, BlockType::AIR);
+    }
+    for y in tree.top()..tree.ground {
+        assert_eq!(world[(tree.x, y)].block_type, log);
+    }
+    assert_eq!(world[(tree.x, tree.top() - 1)].block_type, leaves);
+}
+
+#[test]
+fn only_generated_trees_topple() {
+    let tree = branched_tree();
+    let mut world = world_around((tree.x, tree.ground));
+    let log = TreePart::Log.get_block_type();
+
+    // a log placed by the player against the leaves stays when the trunk is cut
+    world.index_mut_and_render((tree.x - 3, tree.top())).block_type = log;
+    world.index_mut_and_render((tree.x, tree.ground - 1)).block_type = BlockType::AIR;
+    world.fell_tree((tree.x, tree.ground - 1));
+    assert_eq!(world[(tree.x - 3, tree.top())].block_type, log);
+
+    // and a pillar of logs built by the player doesn't fall when its base is broken
+    world.index_mut_and_render((tree.x - 3, tree.top() - 1)).block_type = log;
+    world.index_mut_and_render((tree.x - 3, tree.top())).block_type = BlockType::AIR;
+    assert!(world.fell_tree((tree.x - 3, tree.top())).is_empty());
+    assert_eq!(world[(tree.x - 3, tree.top() - 1)].block_type, log);
+}
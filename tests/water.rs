@@ -0,0 +1,158 @@
+use terrarust::{
+    blocks::BlockType,
+    chunks::{soil_at, surface_height, Chunk, Section, SECTION_SIZE},
+    coords::x_to_chunk_and_column,
+    fluid::settled_level,
+    player::Player,
+    seed::WorldSeed,
+    structures::Structure,
+    trees::is_tree_column,
+    water::{is_near_water, is_shore, Aquifer, Basin, BasinKind, AQUIFER_TOP, BASIN_REGION_WIDTH},
+    world::World,
+};
+
+/// The first basin of a kind east of the spawn holding water.
+fn find_basin(seed: WorldSeed, kind: BasinKind) -> Basin {
+    (0..1000)
+        .filter_map(|region| Basin::in_region(seed, region))
+        .find(|basin| basin.kind == kind && surface_height(seed, basin.x).floor() as isize > basin.water_level(seed))
+        .unwrap()
+}
+
+fn find_aquifer(seed: WorldSeed) -> Aquifer {
+    (0..1000).find_map(|region| Aquifer::in_region(seed, region)).unwrap()
+}
+
+/// A world generated around a position.
+fn world_around(seed: WorldSeed, (x, y): (isize, isize)) -> World {
+    let mut world = World::new(seed);
+    let mut player = Player::new();
+    player.x = x as f64;
+    player.y = y as f64;
+    world.update_chunks(&player);
+    world
+}
+
+fn generate_column(seed: WorldSeed, x: isize, rows: std::ops::Range<isize>) -> (Chunk, usize) {
+    let (chunk_number, column) = x_to_chunk_and_column(x);
+    let mut chunk = Chunk::new();
+    for section in rows.start as usize / SECTION_SIZE..=(rows.end as usize - 1) / SECTION_SIZE {
+        chunk.set_section(section, Section::generate(seed, chunk_number, section));
+    }
+    (chunk, column as usize)
+}
+
+#[test]
+fn basins_are_filled_up_to_their_lowest_edge() {
+    let seed = WorldSeed::default();
+    for kind in [BasinKind::Lake, BasinKind::River].iter() {
+        let basin = find_basin(seed, *kind);
+        let level = basin.water_level(seed);
+        let mut wet_columns = 0;
+        for x in basin.columns() {
+            let surface = surface_height(seed, x).floor() as isize;
+            let (chunk, column) = generate_column(seed, x, 0..surface + 1);
+            let block = |y: isize| chunk.get(column, y as usize).unwrap();
+            for y in 0..surface {
+                let expected = if y >= level { settled_level((y - level) as usize) } else { 0 };
+                assert_eq!(block(y).fluid_level, expected, "wrong level at ({}, {})", x, y);
+            }
+            assert_eq!(block(surface).block_type, BlockType::from_name("sand"));
+            if surface > level {
+                wet_columns += 1;
+            }
+        }
+        assert!(wet_columns > 2, "the {:?} at {} is almost dry", kind, basin.x);
+        for x in [basin.x - basin.radius - 1, basin.x + basin.radius + 1].iter() {
+            assert!(is_shore(seed, *x));
+            assert_eq!(soil_at(seed, *x), ("sand", "sand"));
+        }
+    }
+}
+
+#[test]
+fn generated_water_is_settled() {
+    let seed = WorldSeed::default();
+    let basin = find_basin(seed, BasinKind::Lake);
+    let mut world = world_around(seed, (basin.x, basin.water_level(seed)));
+    let levels = |world: &World| -> Vec<u16> {
+        basin.columns().flat_map(|x| (0..100).map(move |y| (x, y))).map(|position| world[position].fluid_level).collect()
+    };
+    let generated = levels(&world);
+    assert!(generated.iter().any(|level| *level > 0));
+    world.tick_fluids();
+    world.tick_fluids();
+    assert!(world.are_fluids_settled());
+    assert_eq!(levels(&world), generated);
+}
+
+#[test]
+fn aquifers_are_closed_pockets_of_water() {
+    let seed = WorldSeed::default();
+    let aquifer = find_aquifer(seed);
+    let mut world = world_around(seed, (aquifer.x + aquifer.width / 2, aquifer.top));
+    let water: Vec<(isize, isize)> = (aquifer.x..aquifer.x + aquifer.width)
+        .flat_map(|x| (aquifer.top..aquifer.top + aquifer.height).map(move |y| (x, y)))
+        .filter(|position| world[*position].fluid_level > 0)
+        .collect();
+    assert!(water.len() > 50);
+    for &(x, y) in &water {
+        assert_eq!(world[(x, y)].fluid_level, settled_level((y - aquifer.top) as usize));
+    }
+    // the water is enclosed by the walls and the bottom of the pocket
+    for y in aquifer.top..=aquifer.top + aquifer.height {
+        assert!(!world[(aquifer.x - 1, y)].can_pass_through());
+        assert!(!world[(aquifer.x + aquifer.width, y)].can_pass_through());
+    }
+    for _ in 0..8 {
+        world.tick_fluids();
+    }
+    assert!(water.iter().all(|&(x, y)| world[(x, y)].fluid_level == settled_level((y - aquifer.top) as usize)));
+}
+
+#[test]
+fn aquifers_are_found_at_every_depth() {
+    let seed = WorldSeed::default();
+    let tops: Vec<isize> = (-500..500).filter_map(|region| Aquifer::in_region(seed, region)).map(|aquifer| aquifer.top).collect();
+    assert!(tops.len() > 20);
+    assert!(tops.iter().all(|top| AQUIFER_TOP.contains(top)));
+    let (highest, deepest) = (*tops.iter().min().unwrap(), *tops.iter().max().unwrap());
+    assert!(highest < AQUIFER_TOP.start + 50 && deepest > AQUIFER_TOP.end - 50, "aquifers from {} to {}", highest, deepest);
+}
+
+#[test]
+fn aquifers_flood_when_mined_into() {
+    let seed = WorldSeed::default();
+    let aquifer = find_aquifer(seed);
+    let mut world = world_around(seed, (aquifer.x + aquifer.width / 2, aquifer.top));
+    // the lowest water of the middle column
+    let x = aquifer.x + aquifer.width / 2;
+    let y = (aquifer.top..aquifer.top + aquifer.height).rev().find(|y| world[(x, *y)].fluid_level > 0).unwrap();
+    for hole in y + 1..y + 4 {
+        world.index_mut_and_render((x, hole)).block_type = BlockType::AIR;
+        world.wake_fluid((x, hole));
+    }
+    for _ in 0..20 {
+        world.tick_fluids();
+    }
+    assert!(world[(x, y + 3)].fluid_level > 0);
+    assert!(!world.are_fluids_settled());
+}
+
+#[test]
+fn nothing_grows_or_is_built_in_water() {
+    let seed = WorldSeed(5);
+    for x in -3000..3000 {
+        if is_tree_column(seed, x) {
+            assert!(!is_shore(seed, x), "a tree grows at {} in the water", x);
+        }
+    }
+    for region in -100..100 {
+        if let Some(structure) = Structure::in_region(seed, region) {
+            let end = structure.x + structure.get_prefab().width as isize;
+            assert!(!is_near_water(seed, structure.x, end), "the structure at {} is flooded", structure.x);
+        }
+    }
+    let basins = (-50..50).filter_map(|region| Basin::in_region(seed, region)).count();
+    assert!(basins > 10, "only {} basins in {} columns", basins, 100 * BASIN_REGION_WIDTH);
+}
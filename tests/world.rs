@@ -0,0 +1,146 @@
+use terrarust::{
+    blocks::{Block, BlockType, NaturalBackground},
+    chunks::{soil_at, surface_height, Chunk, Section, CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_COUNT, SECTION_SIZE},
+    fluid::Fluid,
+    light::Light,
+    player::Player,
+    seed::WorldSeed,
+    world::World,
+};
+
+#[test]
+fn world_runs_headless() {
+    let mut world = World::default();
+    assert_eq!(world.chunks.len(), 10);
+    assert!(world[(0, 0)].light.brightness() > 90);
+
+    world.index_mut_and_render((0, 45)).block_type = BlockType::AIR;
+    world.update_light((0, 45));
+    world.tick_fluids();
+    assert!(world.blocks_to_render.contains(&(0, 45)));
+}
+
+fn surface(world: &World) -> Vec<usize> {
+    world
+        .chunks
+        .iter()
+        .flat_map(|chunk| (0..CHUNK_WIDTH).map(move |x| (chunk, x)))
+        .map(|(chunk, x)| (0..).find(|y| chunk.get(x, *y).unwrap().block_type != BlockType::AIR).unwrap())
+        .collect()
+}
+
+#[test]
+fn worlds_depend_on_their_seed() {
+    assert_eq!(surface(&World::new(WorldSeed(7))), surface(&World::new(WorldSeed(7))));
+    assert_ne!(surface(&World::new(WorldSeed(7))), surface(&World::new(WorldSeed(8))));
+}
+
+#[test]
+fn terrain_does_not_depend_on_generation_order() {
+    let seed = WorldSeed(3);
+    let mut world = World::new(seed);
+    let mut player = Player::new();
+    player.x = -200.0;
+    world.update_chunks(&player);
+    player.x = 300.0;
+    world.update_chunks(&player);
+
+    for (idx, chunk) in world.chunks.iter().enumerate() {
+        let chunk_number = world.first_chunk_number + idx as isize;
+        for section in (0..SECTION_COUNT).filter(|section| chunk.is_generated(*section)) {
+            let mut generated = Chunk::new();
+            generated.set_section(section, Section::generate(seed, chunk_number, section));
+            for x in 0..CHUNK_WIDTH {
+                for y in section * SECTION_SIZE..(section + 1) * SECTION_SIZE {
+                    assert_eq!(chunk.get(x, y).unwrap().block_type, generated.get(x, y).unwrap().block_type);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn sections_are_generated_near_the_player() {
+    let mut world = World::default();
+    assert!(world.chunks.iter().all(|chunk| chunk.is_generated(0)));
+    assert!(world.chunks.iter().all(|chunk| !chunk.is_generated(SECTION_COUNT - 1)));
+    assert!(!world.is_generated((0, CHUNK_HEIGHT as isize - 1)));
+
+    let mut player = Player::new();
+    player.y = CHUNK_HEIGHT as f64 - 10.0;
+    world.update_chunks(&player);
+    assert!(world.chunks.iter().all(|chunk| chunk.is_generated(SECTION_COUNT - 1)));
+    assert!(world.chunks.iter().all(|chunk| !chunk.is_generated(SECTION_COUNT / 2)));
+    assert!(world.is_generated((0, CHUNK_HEIGHT as isize - 1)));
+}
+
+#[test]
+fn terrain_is_continuous() {
+    let seed = WorldSeed(3);
+    let mut max_step: f64 = 0.0;
+    for x in -2000..2000 {
+        let step = (surface_height(seed, x + 1) - surface_height(seed, x)).abs();
+        max_step = max_step.max(step);
+    }
+    assert!(max_step < 3.0, "the surface jumps by {} blocks", max_step);
+}
+
+#[test]
+fn ores_are_found_in_depth_bands() {
+    let seed = WorldSeed(9);
+    let mut found = Vec::new();
+    let mut soils = Vec::new();
+    for chunk_number in -4..4 {
+        let chunk = Chunk::generate(seed, chunk_number);
+        for (column, x) in (0..CHUNK_WIDTH).zip(chunk_number * 32..) {
+            let surface = surface_height(seed, x).floor() as usize;
+            // the layer under the surface depends on the biome, mountains have none
+            let (_, soil) = soil_at(seed, x);
+            if !soils.contains(&soil) {
+                soils.push(soil);
+            }
+            for y in surface + 1..CHUNK_HEIGHT {
+                let block = chunk.get(column, y).unwrap();
+                let depth = y - surface;
+                match block.block_type.get_name() {
+                    name if name == soil && soil != "stone" => assert!(depth <= 14),
+                    "stone" if soil != "stone" => assert!(depth >= 6),
+                    "coal_ore" => assert!(depth >= 6),
+                    "iron_ore" => assert!(depth >= 46),
+                    "gold_ore" => assert!(depth >= 156),
+                    _ => (),
+                }
+                if !found.contains(&block.block_type.get_name()) {
+                    found.push(block.block_type.get_name());
+                }
+            }
+        }
+    }
+
+    for name in soils.iter().chain(&["stone", "coal_ore", "iron_ore", "gold_ore"]) {
+        assert!(found.contains(name), "no {} generated", name);
+    }
+}
+
+#[test]
+fn chunks_only_allocate_the_blocks_of_varied_sections() {
+    let block = Block {
+        block_type: BlockType::AIR,
+        natural_background: NaturalBackground::Sky,
+        light: Light::BLACK,
+        fluid: Fluid::Water,
+        fluid_level: 0,
+    };
+    assert!(std::mem::size_of::<Block>() <= 10);
+    let mut chunk = Chunk::filled(block);
+    let empty = chunk.heap_size();
+    assert!(empty < 4096);
+    for section in 0..SECTION_COUNT {
+        chunk.set_section(section, Section::Full(vec![block; SECTION_SIZE * SECTION_SIZE]));
+    }
+    assert_eq!(chunk.heap_size(), empty + CHUNK_WIDTH * CHUNK_HEIGHT * std::mem::size_of::<Block>());
+    // blocks took 24 bytes before they were packed
+    assert!(chunk.heap_size() * 2 < CHUNK_WIDTH * CHUNK_HEIGHT * 24);
+    let generated = Chunk::generate(WorldSeed::default(), 3).heap_size();
+    assert!(generated > empty && generated <= chunk.heap_size());
+}